@@ -0,0 +1,208 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{hash_file, Hashes, Result};
+
+// Directory in the data directory holding downloaded files, named by their sha512
+pub const CACHE_DIR: &str = "cache";
+
+// Content-addressed store of downloaded files shared by every directory mdget installs into
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    // Hard link files out of the cache instead of copying them where possible
+    link: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub sha512: String,
+    pub path: PathBuf,
+    pub size: u64,
+    // Updated whenever the entry is installed, so eviction can drop the least recently used
+    pub used: SystemTime,
+}
+
+impl Cache {
+    pub fn open(data_dir: &Path, link: bool) -> Result<Self> {
+        let dir = data_dir.join(CACHE_DIR);
+
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Cache { dir, link })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, sha512: &str) -> PathBuf {
+        self.dir.join(sha512)
+    }
+
+    pub fn contains(&self, sha512: &str) -> bool {
+        !sha512.is_empty() && self.path(sha512).is_file()
+    }
+
+    // Put the cached file with this hash at path, returning its hashes or None if it isn't
+    // cached. An entry that doesn't have the hash anymore, e.g. because a hard linked install
+    // of it was modified, is removed instead.
+    pub fn install(&self, sha512: &str, path: &Path) -> Result<Option<Hashes>> {
+        if !self.contains(sha512) {
+            return Ok(None);
+        }
+
+        let cached = self.path(sha512);
+        let hashes = hash_file(&cached)?;
+
+        if !hashes.sha512.eq_ignore_ascii_case(sha512) {
+            self.remove(sha512)?;
+            return Ok(None);
+        }
+
+        link_or_copy(&cached, path, self.link)?;
+
+        // Mark the entry as recently used
+        let _ = std::fs::File::options()
+            .append(true)
+            .open(&cached)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        Ok(Some(hashes))
+    }
+
+    // Add a file that was verified to have this hash
+    pub fn insert(&self, sha512: &str, path: &Path) -> Result<()> {
+        if sha512.is_empty() || self.contains(sha512) {
+            return Ok(());
+        }
+
+        link_or_copy(path, &self.path(sha512), self.link)
+    }
+
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            // Skip files that are still being written
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            entries.push(CacheEntry {
+                sha512: name,
+                path: entry.path(),
+                size: metadata.len(),
+                used: metadata.modified()?,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    // Total size of the cache in bytes
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|e| e.size).sum())
+    }
+
+    pub fn remove(&self, sha512: &str) -> Result<()> {
+        std::fs::remove_file(self.path(sha512))?;
+        Ok(())
+    }
+
+    // Remove the least recently used entries until the cache fits in limit bytes.
+    // Returns the removed entries.
+    pub fn evict(&self, limit: u64) -> Result<Vec<CacheEntry>> {
+        let mut entries = self.entries()?;
+        let mut size = entries.iter().map(|e| e.size).sum::<u64>();
+
+        entries.sort_by_key(|e| e.used);
+
+        let mut evicted = Vec::new();
+
+        for entry in entries {
+            if size <= limit {
+                break;
+            }
+
+            self.remove(&entry.sha512)?;
+            size -= entry.size;
+            evicted.push(entry);
+        }
+
+        Ok(evicted)
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        for entry in self.entries()? {
+            self.remove(&entry.sha512)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Replace to with from, hard linking if allowed and possible. Goes through a temporary file
+// next to to so it never exists half written.
+fn link_or_copy(from: &Path, to: &Path, link: bool) -> Result<()> {
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = to.with_file_name(format!(".{}.part", name));
+
+    let _ = std::fs::remove_file(&tmp);
+
+    if !link || std::fs::hard_link(from, &tmp).is_err() {
+        std::fs::copy(from, &tmp)?;
+    }
+
+    std::fs::rename(&tmp, to)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modified_entries_are_removed_instead_of_installed() {
+        let data_dir = std::env::temp_dir().join(format!("mdget-cache-{}", std::process::id()));
+        let cache = Cache::open(&data_dir, true).unwrap();
+
+        let download = data_dir.join("a.jar");
+        std::fs::write(&download, b"a").unwrap();
+        let sha512 = hash_file(&download).unwrap().sha512;
+        cache.insert(&sha512, &download).unwrap();
+
+        let installed = data_dir.join("b.jar");
+
+        assert_eq!(
+            cache
+                .install(&sha512, &installed)
+                .unwrap()
+                .map(|h| h.sha512),
+            Some(sha512.clone())
+        );
+
+        // Written through the hard link into the cache
+        std::fs::write(&installed, b"b").unwrap();
+
+        assert_eq!(
+            cache.install(&sha512, &data_dir.join("c.jar")).unwrap(),
+            None
+        );
+        assert!(!cache.contains(&sha512));
+
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,515 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{
+        HeaderMap, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        RETRY_AFTER,
+    },
+    StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    cache::Cache,
+    http_cache::{CachedResponse, HttpCache},
+    Dependencies, Error, MinecraftVersions, ModrinthProject, ProjectVersion, ProjectVersions,
+    Result, SearchResults, API_URL, USER_AGENT, VERSION_MANIFEST_URL,
+};
+
+// Query parameters for /search
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub query: String,
+    // Outer list is ANDed, inner lists are ORed, e.g. [["categories:fabric"], ["versions:1.20.1"]]
+    pub facets: Vec<Vec<String>>,
+    // relevance, downloads, follows, newest or updated
+    pub index: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+// How many times a request that got 429 is sent again after waiting for the rate limit
+const RATE_LIMIT_RETRIES: u32 = 5;
+
+// The API's rate limit budget as of the last response, shared by clones of the client
+#[derive(Debug, Default)]
+struct RateLimit {
+    remaining: Option<u64>,
+    reset: Option<Instant>,
+    // The reset that was already reported, so waiting threads only say so once
+    reported: Option<Instant>,
+}
+
+// Blocking client for the Modrinth v2 API
+#[derive(Debug, Clone)]
+pub struct ModrinthClient {
+    client: Client,
+    api_url: String,
+    version_manifest_url: String,
+    mirrors: Vec<String>,
+    // Personal access token, sent to the API only
+    token: Option<String>,
+    cache: Option<HttpCache>,
+    // Downloaded files by sha512, to install from instead of downloading them again
+    download_cache: Option<Cache>,
+    // Where unfinished downloads are kept for resuming, next to their target if unset
+    partial_dir: Option<PathBuf>,
+    // URL prefix -> headers to download files under it with, e.g. a token for private files
+    download_headers: Vec<(String, HeaderMap)>,
+    rate_limit: Arc<Mutex<RateLimit>>,
+    // Only answer from the cache, never touch the network
+    offline: bool,
+}
+
+impl ModrinthClient {
+    pub fn new() -> reqwest::Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+
+        // User-Agent: kalkafox/mdget/0.1.0
+        headers.insert(
+            reqwest::header::USER_AGENT,
+            reqwest::header::HeaderValue::from_static(USER_AGENT),
+        );
+
+        let client = Client::builder()
+            // timeout after 5 seconds
+            .timeout(std::time::Duration::from_secs(5))
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self::with_client(client))
+    }
+
+    // Wrap an existing reqwest client, which should already send a User-Agent
+    pub fn with_client(client: Client) -> Self {
+        ModrinthClient {
+            client,
+            api_url: API_URL.to_string(),
+            version_manifest_url: VERSION_MANIFEST_URL.to_string(),
+            mirrors: Vec::new(),
+            token: None,
+            cache: None,
+            download_cache: None,
+            partial_dir: None,
+            download_headers: Vec::new(),
+            rate_limit: Arc::default(),
+            offline: false,
+        }
+    }
+
+    // Talk to a Modrinth-compatible API other than api.modrinth.com
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn version_manifest_url(mut self, url: &str) -> Self {
+        self.version_manifest_url = url.to_string();
+        self
+    }
+
+    // Hosts to download files from when their own URL fails
+    pub fn mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    // A file's own URL followed by the same path on every mirror
+    pub fn download_urls(&self, url: &str) -> Vec<String> {
+        let mut urls = vec![url.to_string()];
+
+        // Local files and the like aren't mirrored
+        let parsed = Url::parse(url)
+            .ok()
+            .filter(|parsed| parsed.scheme() == "http" || parsed.scheme() == "https");
+
+        if let Some(parsed) = parsed {
+            let query = parsed
+                .query()
+                .map(|query| format!("?{}", query))
+                .unwrap_or_default();
+
+            for mirror in &self.mirrors {
+                urls.push(format!(
+                    "{}{}{}",
+                    mirror.trim_end_matches('/'),
+                    parsed.path(),
+                    query
+                ));
+            }
+        }
+
+        urls
+    }
+
+    // Authenticate with a Modrinth personal access token, which lets private and draft
+    // projects be seen
+    pub fn token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    // Cache GET responses on disk
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_download_cache(mut self, cache: Cache) -> Self {
+        self.download_cache = Some(cache);
+        self
+    }
+
+    pub fn download_cache(&self) -> Option<&Cache> {
+        self.download_cache.as_ref()
+    }
+
+    pub fn with_partial_dir(mut self, dir: PathBuf) -> Self {
+        self.partial_dir = Some(dir);
+        self
+    }
+
+    pub fn partial_dir(&self) -> Option<&Path> {
+        self.partial_dir.as_deref()
+    }
+
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    // The underlying HTTP client, for requests outside the API such as file downloads
+    pub fn http(&self) -> &Client {
+        &self.client
+    }
+
+    // Send headers along when downloading files whose URL starts with prefix
+    pub fn with_download_headers(mut self, prefix: &str, headers: HeaderMap) -> Self {
+        self.download_headers.push((prefix.to_string(), headers));
+        self
+    }
+
+    // A GET request for a file, with the download headers for its URL
+    pub fn download(&self, url: &str) -> RequestBuilder {
+        let mut req = self.client.get(url);
+
+        for (prefix, headers) in &self.download_headers {
+            if url.starts_with(prefix.as_str()) {
+                req = req.headers(headers.clone());
+            }
+        }
+
+        req
+    }
+
+    // Minecraft versions from Mojang's version manifest
+    pub fn minecraft_versions(&self) -> Result<MinecraftVersions> {
+        self.fetch(&self.version_manifest_url)
+    }
+
+    // Project by slug or id
+    pub fn project(&self, id: &str) -> Result<ModrinthProject> {
+        self.get(&format!("/project/{}", id))
+    }
+
+    pub fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        self.get(&format!("/project/{}/version", id))
+    }
+
+    pub fn dependencies(&self, id: &str) -> Result<Dependencies> {
+        self.get(&format!("/project/{}/dependencies", id))
+    }
+
+    pub fn version(&self, id: &str) -> Result<ProjectVersion> {
+        self.get(&format!("/version/{}", id))
+    }
+
+    // Version containing the file with this hash. algorithm is sha1 or sha512.
+    pub fn version_file_by_hash(&self, hash: &str, algorithm: &str) -> Result<ProjectVersion> {
+        self.get(&format!("/version_file/{}?algorithm={}", hash, algorithm))
+    }
+
+    // Versions containing the files with these hashes, keyed by hash
+    pub fn version_files(
+        &self,
+        hashes: &[String],
+        algorithm: &str,
+    ) -> Result<HashMap<String, ProjectVersion>> {
+        let url = format!("{}/version_files", self.api_url);
+
+        if self.offline {
+            return Err(Error::Offline { url });
+        }
+
+        let body = serde_json::json!({ "hashes": hashes, "algorithm": algorithm });
+
+        let resp = self
+            .send(&url, || self.client.post(&url).json(&body))
+            .map_err(Error::Network)?;
+
+        if !resp.status().is_success() {
+            return Err(Error::Status {
+                url,
+                status: resp.status(),
+            });
+        }
+
+        let body = resp.text().map_err(Error::Network)?;
+
+        Self::decode(&url, &body)
+    }
+
+    // Several projects by id or slug at once
+    pub fn projects(&self, ids: &[String]) -> Result<Vec<ModrinthProject>> {
+        let ids = serde_json::to_string(ids).unwrap_or_default();
+
+        self.get_with(&format!("{}/projects", self.api_url), &[("ids", ids)])
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResults> {
+        let mut params = vec![("query", query.query.clone())];
+
+        if !query.facets.is_empty() {
+            // Facets are sent as a JSON array of arrays
+            params.push((
+                "facets",
+                serde_json::to_string(&query.facets).unwrap_or_default(),
+            ));
+        }
+
+        if let Some(index) = &query.index {
+            params.push(("index", index.clone()));
+        }
+
+        if let Some(limit) = query.limit {
+            params.push(("limit", limit.to_string()));
+        }
+
+        if let Some(offset) = query.offset {
+            params.push(("offset", offset.to_string()));
+        }
+
+        self.get_with(&format!("{}/search", self.api_url), &params)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.fetch(&format!("{}{}", self.api_url, path))
+    }
+
+    fn get_with<T: DeserializeOwned>(&self, url: &str, params: &[(&str, String)]) -> Result<T> {
+        match Url::parse_with_params(url, params) {
+            Ok(url) => self.fetch(url.as_str()),
+            Err(_) => self.fetch(url),
+        }
+    }
+
+    // GET a JSON document from any URL. Cached responses are used as is while they are
+    // fresh, revalidated once they aren't, and used regardless when offline or if the
+    // server can't be reached.
+    pub fn fetch<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.fetch_with_headers(url, &HeaderMap::new())
+    }
+
+    // fetch, sending extra headers such as another API's key
+    pub fn fetch_with_headers<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> Result<T> {
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(url));
+
+        if let (Some(cache), Some(cached)) = (&self.cache, &cached) {
+            if self.offline || cache.is_fresh(cached) {
+                return Self::decode(url, &cached.body);
+            }
+        }
+
+        if self.offline {
+            return Err(Error::Offline {
+                url: url.to_string(),
+            });
+        }
+
+        let req = || {
+            let mut req = self.client.get(url).headers(headers.clone());
+
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.etag {
+                    req = req.header(IF_NONE_MATCH, etag);
+                }
+
+                if let Some(last_modified) = &cached.last_modified {
+                    req = req.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            req
+        };
+
+        let resp = match self.send(url, req) {
+            Ok(resp) => resp,
+            // Better stale than nothing
+            Err(err) => {
+                return match cached {
+                    Some(cached) => Self::decode(url, &cached.body),
+                    None => Err(Error::Network(err)),
+                }
+            }
+        };
+
+        if let (StatusCode::NOT_MODIFIED, Some(mut cached)) = (resp.status(), cached) {
+            cached.fetched_at = chrono::Utc::now().timestamp();
+
+            if let Some(cache) = &self.cache {
+                cache.put(&cached);
+            }
+
+            return Self::decode(url, &cached.body);
+        }
+
+        if !resp.status().is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status: resp.status(),
+            });
+        }
+
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let body = resp.text().map_err(Error::Network)?;
+
+        let value = Self::decode(url, &body)?;
+
+        if let Some(cache) = &self.cache {
+            cache.put(&CachedResponse {
+                url: url.to_string(),
+                etag,
+                last_modified,
+                fetched_at: chrono::Utc::now().timestamp(),
+                body,
+            });
+        }
+
+        Ok(value)
+    }
+
+    // Send the request built by build. Requests to the API carry the token, if there is one,
+    // and wait for the rate limit to reset instead of getting 429. The token isn't handed to
+    // other hosts such as CDNs or mirrors.
+    fn send(&self, url: &str, build: impl Fn() -> RequestBuilder) -> reqwest::Result<Response> {
+        if !url.starts_with(&self.api_url) {
+            return build().send();
+        }
+
+        let mut attempts = 0;
+
+        loop {
+            self.wait_for_rate_limit();
+
+            let req = match &self.token {
+                Some(token) => build().header(AUTHORIZATION, token),
+                None => build(),
+            };
+
+            let resp = req.send()?;
+
+            self.update_rate_limit(&resp);
+
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS && attempts < RATE_LIMIT_RETRIES {
+                attempts += 1;
+                continue;
+            }
+
+            return Ok(resp);
+        }
+    }
+
+    // Sleep until the rate limit resets if the budget is used up, otherwise take from it
+    fn wait_for_rate_limit(&self) {
+        loop {
+            let wait = {
+                let mut limit = self.rate_limit.lock().unwrap();
+                let now = Instant::now();
+
+                match (limit.remaining, limit.reset) {
+                    (Some(0), Some(reset)) if reset > now => {
+                        if limit.reported != Some(reset) {
+                            limit.reported = Some(reset);
+
+                            crate::cwarnln(
+                                format!(
+                                    "Hit the Modrinth rate limit, waiting {}s...",
+                                    (reset - now).as_secs_f32().ceil()
+                                )
+                                .as_str(),
+                            );
+                        }
+
+                        reset - now
+                    }
+                    (Some(0), _) => {
+                        // The limit reset, the next response tells us the new budget
+                        limit.remaining = None;
+                        return;
+                    }
+                    (Some(remaining), _) => {
+                        limit.remaining = Some(remaining - 1);
+                        return;
+                    }
+                    (None, _) => return,
+                }
+            };
+
+            std::thread::sleep(wait);
+        }
+    }
+
+    // Track the budget from the X-Ratelimit headers, or Retry-After on 429
+    fn update_rate_limit(&self, resp: &Response) {
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        let remaining = header("x-ratelimit-remaining");
+        // Seconds until the limit resets
+        let reset = header("x-ratelimit-reset").or_else(|| header(RETRY_AFTER.as_str()));
+
+        let mut limit = self.rate_limit.lock().unwrap();
+
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            limit.remaining = Some(0);
+            limit.reset = Some(Instant::now() + Duration::from_secs(reset.unwrap_or(60).max(1)));
+        } else if let Some(remaining) = remaining {
+            limit.remaining = Some(remaining);
+            limit.reset = reset.map(|reset| Instant::now() + Duration::from_secs(reset));
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(url: &str, body: &str) -> Result<T> {
+        serde_json::from_str(body).map_err(|source| Error::Decode {
+            url: url.to_string(),
+            source,
+        })
+    }
+}
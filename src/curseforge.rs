@@ -0,0 +1,693 @@
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    source::ModSource, Dependency, Error, File, Hashes, ModrinthClient, ModrinthProject,
+    ProjectVersion, ProjectVersions, Result,
+};
+
+pub const CURSEFORGE_API_URL: &str = "https://api.curseforge.com/v1";
+
+// Prefix of CurseForge project ids, e.g. curseforge:238222
+pub const CURSEFORGE: &str = "curseforge";
+
+// Minecraft's game id and the mods class, as opposed to resource packs, worlds and so on
+const MINECRAFT: u32 = 432;
+const MODS_CLASS: u32 = 6;
+
+const PAGE_SIZE: usize = 50;
+
+// Blocking client for the CurseForge Core API. Requests go through the Modrinth client so
+// they share its cache and offline mode.
+#[derive(Debug, Clone)]
+pub struct CurseForgeClient {
+    client: ModrinthClient,
+    api_url: String,
+    // Needed for every request, from https://console.curseforge.com
+    api_key: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Response<T> {
+    data: T,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Page<T> {
+    data: Vec<T>,
+    pagination: Pagination,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Pagination {
+    index: usize,
+    page_size: usize,
+    result_count: usize,
+    total_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CurseForgeMod {
+    pub id: u64,
+    pub name: String,
+    pub slug: String,
+    pub summary: String,
+    pub links: Links,
+    pub download_count: f64,
+    pub categories: Vec<Category>,
+    pub class_id: Option<u32>,
+    pub logo: Option<Logo>,
+    pub date_created: String,
+    pub date_modified: String,
+    pub latest_files_indexes: Vec<FileIndex>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Links {
+    pub website_url: Option<String>,
+    pub wiki_url: Option<String>,
+    pub issues_url: Option<String>,
+    pub source_url: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Category {
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Logo {
+    pub url: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FileIndex {
+    pub game_version: String,
+    pub mod_loader: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CurseForgeFile {
+    pub id: u64,
+    pub mod_id: u64,
+    pub display_name: String,
+    pub file_name: String,
+    // 1 release, 2 beta, 3 alpha
+    pub release_type: u32,
+    pub hashes: Vec<FileHash>,
+    pub file_date: String,
+    pub file_length: i64,
+    // None if the author doesn't allow downloads outside of CurseForge's own launcher
+    pub download_url: Option<String>,
+    // Game versions mixed with loaders and sides, e.g. ["1.20.1", "Fabric", "Client"]
+    pub game_versions: Vec<String>,
+    pub dependencies: Vec<FileDependency>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FileHash {
+    pub value: String,
+    // 1 sha1, 2 md5
+    pub algo: u32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FileDependency {
+    pub mod_id: u64,
+    // 1 embedded, 2 optional, 3 required, 4 tool, 5 incompatible, 6 include
+    pub relation_type: u32,
+}
+
+// Project id of a CurseForge mod as the other sources see it
+pub fn project_id(mod_id: u64) -> String {
+    format!("{}:{}", CURSEFORGE, mod_id)
+}
+
+// Loader names by CurseForge's ModLoaderType
+fn loader_name(loader: u32) -> Option<&'static str> {
+    match loader {
+        1 => Some("forge"),
+        3 => Some("liteloader"),
+        4 => Some("fabric"),
+        5 => Some("quilt"),
+        6 => Some("neoforge"),
+        _ => None,
+    }
+}
+
+const LOADERS: &[&str] = &["forge", "fabric", "quilt", "neoforge", "liteloader"];
+
+// CurseForge's ModLoaderType of a loader
+fn loader_type(loader: &str) -> Option<u32> {
+    (1..=6).find(|&loader_type| loader_name(loader_type) == Some(loader))
+}
+
+impl CurseForgeMod {
+    pub fn project(&self) -> ModrinthProject {
+        let mut game_versions = Vec::new();
+        let mut loaders = Vec::new();
+
+        for index in &self.latest_files_indexes {
+            if !game_versions.contains(&index.game_version) {
+                game_versions.push(index.game_version.clone());
+            }
+
+            if let Some(loader) = index.mod_loader.and_then(loader_name) {
+                if !loaders.iter().any(|l| l == loader) {
+                    loaders.push(loader.to_string());
+                }
+            }
+        }
+
+        ModrinthProject {
+            id: project_id(self.id),
+            slug: self.slug.clone(),
+            project_type: "mod".to_string(),
+            title: self.name.clone(),
+            description: self.summary.clone(),
+            published: self.date_created.clone(),
+            updated: self.date_modified.clone(),
+            status: "approved".to_string(),
+            // CurseForge doesn't say which sides a mod is for
+            client_side: "unknown".to_string(),
+            server_side: "unknown".to_string(),
+            downloads: self.download_count as i64,
+            categories: self.categories.iter().map(|c| c.slug.clone()).collect(),
+            game_versions,
+            loaders,
+            icon_url: self.logo.as_ref().map(|logo| logo.url.clone()),
+            issues_url: self.links.issues_url.clone(),
+            source_url: self.links.source_url.clone(),
+            wiki_url: self.links.wiki_url.clone().into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl CurseForgeFile {
+    pub fn version(&self) -> ProjectVersion {
+        let mut game_versions = Vec::new();
+        let mut loaders = Vec::new();
+
+        for version in &self.game_versions {
+            let lower = version.to_lowercase();
+
+            if LOADERS.contains(&lower.as_str()) {
+                loaders.push(lower);
+            } else if lower != "client" && lower != "server" {
+                game_versions.push(version.clone());
+            }
+        }
+
+        let dependencies = self
+            .dependencies
+            .iter()
+            .filter_map(|dependency| {
+                let kind = match dependency.relation_type {
+                    1 | 6 => "embedded",
+                    2 => "optional",
+                    3 => "required",
+                    5 => "incompatible",
+                    _ => return None,
+                };
+
+                Some(Dependency {
+                    version_id: None,
+                    project_id: Some(project_id(dependency.mod_id)),
+                    file_name: None,
+                    dependency_type: Some(kind.to_string()),
+                })
+            })
+            .collect();
+
+        let sha1 = self
+            .hashes
+            .iter()
+            .find(|hash| hash.algo == 1)
+            .map(|hash| hash.value.clone())
+            .unwrap_or_default();
+
+        // Files that can't be downloaded from outside CurseForge have no URL
+        let files = match &self.download_url {
+            Some(url) => vec![File {
+                hashes: Hashes {
+                    sha512: String::new(),
+                    sha1,
+                },
+                url: url.clone(),
+                filename: self.file_name.clone(),
+                primary: true,
+                size: self.file_length,
+                file_type: None,
+                mirrors: Vec::new(),
+                local: None,
+            }],
+            None => Vec::new(),
+        };
+
+        ProjectVersion {
+            name: Some(self.display_name.clone()),
+            version_number: Some(self.display_name.clone()),
+            dependencies,
+            game_versions,
+            version_type: Some(
+                match self.release_type {
+                    2 => "beta",
+                    3 => "alpha",
+                    _ => "release",
+                }
+                .to_string(),
+            ),
+            loaders,
+            id: Some(self.id.to_string()),
+            project_id: Some(project_id(self.mod_id)),
+            date_published: Some(self.file_date.clone()),
+            files,
+            ..Default::default()
+        }
+    }
+}
+
+impl CurseForgeClient {
+    pub fn new(client: ModrinthClient, api_key: Option<String>) -> Self {
+        CurseForgeClient {
+            client,
+            api_url: CURSEFORGE_API_URL.to_string(),
+            api_key,
+        }
+    }
+
+    // Talk to another CurseForge-compatible API, e.g. a proxy that adds the key itself
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    // Mod by id or slug
+    pub fn get_mod(&self, id: &str) -> Result<CurseForgeMod> {
+        if let Ok(id) = id.parse::<u64>() {
+            return self
+                .get::<Response<CurseForgeMod>>(&format!("/mods/{}", id))
+                .map(|resp| resp.data);
+        }
+
+        let params = [
+            ("gameId", MINECRAFT.to_string()),
+            ("classId", MODS_CLASS.to_string()),
+            ("slug", id.to_string()),
+        ];
+
+        self.get_with::<Page<CurseForgeMod>>("/mods/search", &params)?
+            .data
+            .into_iter()
+            .find(|m| m.slug == id)
+            .ok_or_else(|| Error::Status {
+                url: self.url("/mods/search", &params),
+                status: StatusCode::NOT_FOUND,
+            })
+    }
+
+    // The files of a mod for a game version and loader if given, or every file it has, newest
+    // first
+    pub fn files(
+        &self,
+        mod_id: u64,
+        game_version: Option<&str>,
+        loader: Option<&str>,
+    ) -> Result<Vec<CurseForgeFile>> {
+        let path = format!("/mods/{}/files", mod_id);
+        let mut filters = Vec::new();
+
+        if let Some(game_version) = game_version {
+            filters.push(("gameVersion", game_version.to_string()));
+        }
+
+        if let Some(loader_type) = loader.and_then(loader_type) {
+            filters.push(("modLoaderType", loader_type.to_string()));
+        }
+
+        let mut files = Vec::new();
+
+        loop {
+            let mut params = filters.clone();
+            params.push(("index", files.len().to_string()));
+            params.push(("pageSize", PAGE_SIZE.to_string()));
+
+            let page = self.get_with::<Page<CurseForgeFile>>(&path, &params)?;
+
+            let done = page.data.len() < PAGE_SIZE
+                || files.len() + page.data.len() >= page.pagination.total_count;
+
+            files.extend(page.data);
+
+            if done {
+                return Ok(files);
+            }
+        }
+    }
+
+    pub fn file(&self, mod_id: u64, file_id: &str) -> Result<CurseForgeFile> {
+        self.get::<Response<CurseForgeFile>>(&format!("/mods/{}/files/{}", mod_id, file_id))
+            .map(|resp| resp.data)
+    }
+
+    // Numeric id of a mod given by id or slug
+    fn mod_id(&self, id: &str) -> Result<u64> {
+        match id.parse::<u64>() {
+            Ok(id) => Ok(id),
+            Err(_) => Ok(self.get_mod(id)?.id),
+        }
+    }
+
+    // URL of an API path with the query parameters encoded
+    fn url(&self, path: &str, params: &[(&str, String)]) -> String {
+        let url = format!("{}{}", self.api_url, path);
+
+        if params.is_empty() {
+            return url;
+        }
+
+        match Url::parse_with_params(&url, params) {
+            Ok(url) => url.to_string(),
+            Err(_) => url,
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.get_with(path, &[])
+    }
+
+    fn get_with<T: DeserializeOwned>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        let api_key = self.api_key.as_deref().ok_or_else(|| Error::NoApiKey {
+            name: CURSEFORGE.to_string(),
+        })?;
+
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_str(api_key).map_err(|_| Error::NoApiKey {
+                name: CURSEFORGE.to_string(),
+            })?,
+        );
+
+        self.client
+            .fetch_with_headers(&self.url(path, params), &headers)
+    }
+}
+
+impl ModSource for CurseForgeClient {
+    fn name(&self) -> &str {
+        CURSEFORGE
+    }
+
+    fn project(&self, id: &str) -> Result<ModrinthProject> {
+        Ok(self.get_mod(id)?.project())
+    }
+
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        Ok(self
+            .files(self.mod_id(id)?, None, None)?
+            .iter()
+            .map(CurseForgeFile::version)
+            .collect())
+    }
+
+    // Only the files for the game version and loader are fetched, instead of every file the
+    // mod has
+    fn supported_versions(
+        &self,
+        id: &str,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<ProjectVersions> {
+        Ok(self
+            .files(self.mod_id(id)?, Some(game_version), Some(loader))?
+            .iter()
+            .map(CurseForgeFile::version)
+            .filter(|v| v.supports(game_version, loader))
+            .collect())
+    }
+
+    fn version(&self, project: &str, id: &str) -> Result<ProjectVersion> {
+        Ok(self.file(self.mod_id(project)?, id)?.version())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn file() -> CurseForgeFile {
+        CurseForgeFile {
+            id: 4_000_001,
+            mod_id: 238_222,
+            display_name: "jei-1.20.1-fabric-15.2.0.27".to_string(),
+            file_name: "jei-1.20.1-fabric-15.2.0.27.jar".to_string(),
+            release_type: 2,
+            hashes: vec![
+                FileHash {
+                    value: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
+                    algo: 2,
+                },
+                FileHash {
+                    value: "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+                    algo: 1,
+                },
+            ],
+            file_date: "2024-01-01T00:00:00Z".to_string(),
+            file_length: 1234,
+            download_url: Some("https://edge.forgecdn.net/files/4000/1/jei.jar".to_string()),
+            game_versions: vec![
+                "1.20.1".to_string(),
+                "Fabric".to_string(),
+                "Client".to_string(),
+                "Server".to_string(),
+            ],
+            dependencies: vec![
+                FileDependency {
+                    mod_id: 1,
+                    relation_type: 3,
+                },
+                FileDependency {
+                    mod_id: 2,
+                    relation_type: 2,
+                },
+                FileDependency {
+                    mod_id: 3,
+                    relation_type: 5,
+                },
+                FileDependency {
+                    mod_id: 4,
+                    relation_type: 6,
+                },
+                FileDependency {
+                    mod_id: 5,
+                    relation_type: 4,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn file_version_is_normalized() {
+        let version = file().version();
+
+        assert_eq!(version.id.as_deref(), Some("4000001"));
+        assert_eq!(version.project_id.as_deref(), Some("curseforge:238222"));
+        assert_eq!(version.version_type.as_deref(), Some("beta"));
+        assert_eq!(version.game_versions, vec!["1.20.1"]);
+        assert_eq!(version.loaders, vec!["fabric"]);
+        assert!(version.supports("1.20.1", "fabric"));
+
+        let file = version.primary_file().unwrap();
+
+        assert_eq!(file.filename, "jei-1.20.1-fabric-15.2.0.27.jar");
+        assert_eq!(file.hashes.sha1, "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert!(file.hashes.sha512.is_empty());
+        assert_eq!(file.size, 1234);
+
+        let dependencies = version
+            .dependencies
+            .iter()
+            .map(|d| {
+                (
+                    d.project_id.as_deref().unwrap(),
+                    d.dependency_type.as_deref().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            dependencies,
+            vec![
+                ("curseforge:1", "required"),
+                ("curseforge:2", "optional"),
+                ("curseforge:3", "incompatible"),
+                ("curseforge:4", "embedded"),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_without_download_url_have_no_files() {
+        let version = CurseForgeFile {
+            download_url: None,
+            ..file()
+        }
+        .version();
+
+        assert!(version.files.is_empty());
+    }
+
+    // Answer requests with the body for their path, or 404, and 401 without the API key
+    fn serve(routes: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request = Vec::new();
+
+                loop {
+                    let mut line = String::new();
+
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+
+                    request.push(line.trim_end().to_string());
+                }
+
+                let path = request[0].split(' ').nth(1).unwrap_or_default();
+                let authorized = request.iter().any(|h| h == "x-api-key: key");
+                let body = routes.iter().find(|(p, _)| *p == path).map(|(_, b)| b);
+
+                let (status, body) = match body {
+                    _ if !authorized => ("401 Unauthorized", ""),
+                    Some(body) => ("200 OK", body.as_str()),
+                    None => ("404 Not Found", ""),
+                };
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn source_against_mock_server() {
+        let jei = CurseForgeMod {
+            id: 238_222,
+            name: "Just Enough Items".to_string(),
+            slug: "jei".to_string(),
+            ..Default::default()
+        };
+
+        let weird = CurseForgeMod {
+            id: 1,
+            slug: "a b&c".to_string(),
+            ..Default::default()
+        };
+
+        let url = serve(vec![
+            (
+                "/mods/search?gameId=432&classId=6&slug=jei",
+                serde_json::json!({ "data": [jei], "pagination": { "totalCount": 1 } }).to_string(),
+            ),
+            (
+                "/mods/238222/files?index=0&pageSize=50",
+                serde_json::json!({ "data": [file()], "pagination": { "totalCount": 1 } })
+                    .to_string(),
+            ),
+            (
+                "/mods/238222/files?gameVersion=1.20.1&modLoaderType=4&index=0&pageSize=50",
+                serde_json::json!({ "data": [file()], "pagination": { "totalCount": 1 } })
+                    .to_string(),
+            ),
+            (
+                "/mods/search?gameId=432&classId=6&slug=a+b%26c",
+                serde_json::json!({ "data": [weird], "pagination": { "totalCount": 1 } })
+                    .to_string(),
+            ),
+            (
+                "/mods/238222/files/4000001",
+                serde_json::json!({ "data": file() }).to_string(),
+            ),
+        ]);
+
+        let client = ModrinthClient::new().unwrap();
+        let source = CurseForgeClient::new(client.clone(), Some("key".to_string())).api_url(&url);
+
+        let project = source.project("jei").unwrap();
+
+        assert_eq!(project.id, "curseforge:238222");
+        assert_eq!(project.title, "Just Enough Items");
+
+        let versions = source.project_versions("jei").unwrap();
+
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].id.as_deref(), Some("4000001"));
+
+        let version = source.version("238222", "4000001").unwrap();
+
+        assert_eq!(version, versions[0]);
+
+        // Only the files for the game version and loader are asked for
+        let supported = source
+            .supported_versions("jei", "1.20.1", "fabric")
+            .unwrap();
+
+        assert_eq!(supported, versions);
+
+        // Slugs are encoded in the query
+        assert_eq!(source.project("a b&c").unwrap().id, "curseforge:1");
+
+        assert!(matches!(
+            source.project("unknown"),
+            Err(Error::Status { .. })
+        ));
+
+        // A wrong key is refused by the server, a missing one never leaves mdget
+        let wrong_key =
+            CurseForgeClient::new(client.clone(), Some("other".to_string())).api_url(&url);
+
+        assert!(matches!(
+            wrong_key.project("jei"),
+            Err(Error::Status { status, .. }) if status == StatusCode::UNAUTHORIZED
+        ));
+
+        let no_key = CurseForgeClient::new(client, None).api_url(&url);
+
+        assert!(matches!(no_key.project("jei"), Err(Error::NoApiKey { .. })));
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    hash_file, source::ModSource, Error, File, Hashes, ModrinthProject, ProjectVersion,
+    ProjectVersions, Result,
+};
+
+// Files downloaded straight from a URL, e.g. url:https://maven.example.com/mymod-1.0.jar
+pub const URL: &str = "url";
+
+// Jars on disk, e.g. file:libs/mymod.jar, relative to the directory mdget runs in
+pub const LOCAL: &str = "file";
+
+// Nothing is known about a file behind a URL but its name, so it has to be pinned with a
+// sha512 requirement in the manifest to be checked against.
+#[derive(Debug, Clone, Default)]
+pub struct UrlSource;
+
+// Copies jars from disk, hashing them up front
+#[derive(Debug, Clone, Default)]
+pub struct LocalSource;
+
+// A project for a single file, named after it
+fn file_project(source: &str, id: &str, filename: &str) -> ModrinthProject {
+    let slug = filename.strip_suffix(".jar").unwrap_or(filename);
+
+    ModrinthProject {
+        id: format!("{}:{}", source, id),
+        slug: slug.to_string(),
+        project_type: "mod".to_string(),
+        title: filename.to_string(),
+        status: "approved".to_string(),
+        client_side: "unknown".to_string(),
+        server_side: "unknown".to_string(),
+        ..Default::default()
+    }
+}
+
+// A version with a single file and no game versions or loaders, so it is used for any
+fn file_version(project_id: String, id: String, file: File) -> ProjectVersion {
+    ProjectVersion {
+        name: Some(file.filename.clone()),
+        version_number: Some(file.filename.clone()),
+        version_type: Some("release".to_string()),
+        id: Some(id),
+        project_id: Some(project_id),
+        files: vec![file],
+        ..Default::default()
+    }
+}
+
+fn filename(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+impl ModSource for UrlSource {
+    fn name(&self) -> &str {
+        URL
+    }
+
+    fn project(&self, id: &str) -> Result<ModrinthProject> {
+        if !(id.starts_with("https://") || id.starts_with("http://")) {
+            return Err(Error::BadProjectId {
+                id: format!("{}:{}", URL, id),
+                expected: "url:https://...".to_string(),
+            });
+        }
+
+        // Without the query, e.g. ?token=...
+        let path = id.split(['?', '#']).next().unwrap_or(id);
+
+        Ok(file_project(URL, id, filename(path)))
+    }
+
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        let project = self.project(id)?;
+
+        Ok(vec![file_version(
+            project.id,
+            project.title.clone(),
+            File {
+                url: id.to_string(),
+                filename: project.title,
+                primary: true,
+                ..Default::default()
+            },
+        )])
+    }
+
+    // The file name is the only version there is
+    fn version(&self, project: &str, id: &str) -> Result<ProjectVersion> {
+        self.project_versions(project)?
+            .into_iter()
+            .find(|version| version.id.as_deref() == Some(id))
+            .ok_or_else(|| Error::NoSuchVersion {
+                project: project.to_string(),
+                requirement: id.to_string(),
+            })
+    }
+
+    fn requires_hash(&self) -> bool {
+        true
+    }
+}
+
+impl ModSource for LocalSource {
+    fn name(&self) -> &str {
+        LOCAL
+    }
+
+    fn project(&self, id: &str) -> Result<ModrinthProject> {
+        if !Path::new(id).is_file() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} isn't a file", id),
+            )));
+        }
+
+        Ok(file_project(LOCAL, id, filename(id)))
+    }
+
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        let project = self.project(id)?;
+        let hashes: Hashes = hash_file(Path::new(id))?;
+        let size = std::fs::metadata(id)?.len() as i64;
+
+        // Versions are told apart by their content, so a changed jar is an update
+        Ok(vec![file_version(
+            project.id,
+            hashes.sha512[..16].to_string(),
+            File {
+                hashes,
+                url: format!("{}:{}", LOCAL, id),
+                filename: project.title,
+                primary: true,
+                size,
+                file_type: None,
+                mirrors: Vec::new(),
+                local: Some(PathBuf::from(id)),
+            },
+        )])
+    }
+
+    fn version(&self, project: &str, id: &str) -> Result<ProjectVersion> {
+        self.project_versions(project)?
+            .into_iter()
+            .find(|version| version.id.as_deref() == Some(id))
+            .ok_or_else(|| Error::NoSuchVersion {
+                project: project.to_string(),
+                requirement: id.to_string(),
+            })
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    fs::OpenOptions,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use console::style;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use reqwest::{header::RANGE, StatusCode};
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+
+use crate::{cache::Cache, pool, Config, Error, File, Hashes, ModrinthClient, Result};
+
+// Directory in the data directory where unfinished downloads are kept for resuming
+pub const PARTIAL_DIR: &str = "partial";
+
+// Download files into dir with at most config.concurrency at once, showing a progress bar for
+// each file and one for the whole batch. Files already in the client's download cache are
+// installed from there.
+// Returns the hashes of each file in order, for files that only came with a sha1.
+pub fn download_all(
+    client: &ModrinthClient,
+    config: &Config,
+    files: &[File],
+    dir: &Path,
+) -> Vec<Result<Hashes>> {
+    if files.is_empty() {
+        return Vec::new();
+    }
+
+    let target = if crate::is_quiet() {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    };
+
+    let multi = MultiProgress::with_draw_target(target);
+
+    let total = files.iter().map(|file| file.size.max(0) as u64).sum();
+
+    let overall = multi.add(
+        ProgressBar::new(total)
+            .with_style(bar_style(
+                "{msg} [{bar:40.green/white}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+            ))
+            .with_message(format!("{} files", files.len())),
+    );
+
+    let batch = Batch {
+        client,
+        config,
+        cache: client.download_cache(),
+        partial_dir: partial_dir(client.partial_dir(), dir),
+        multi: &multi,
+        overall: &overall,
+    };
+
+    let results = pool::map(files, config.concurrency, |file| batch.fetch(file, dir));
+
+    overall.finish_and_clear();
+
+    if let Some(cache) = batch.cache {
+        if let Err(err) = cache.evict(config.cache_size * 1024 * 1024) {
+            crate::cwarnln(format!("Failed to trim the download cache: {}", err).as_str());
+        }
+    }
+
+    results
+}
+
+struct Batch<'a> {
+    client: &'a ModrinthClient,
+    config: &'a Config,
+    cache: Option<&'a Cache>,
+    partial_dir: PathBuf,
+    multi: &'a MultiProgress,
+    overall: &'a ProgressBar,
+}
+
+impl Batch<'_> {
+    // Install a file into dir from the cache, or download it from its URL or the mirrors
+    fn fetch(&self, file: &File, dir: &Path) -> Result<Hashes> {
+        let path = dir.join(&file.filename);
+
+        // The cache is keyed by sha512, files without one are only cached once downloaded
+        if let Some(cache) = self.cache.filter(|_| !file.hashes.sha512.is_empty()) {
+            if let Ok(Some(hashes)) = cache.install(&file.hashes.sha512, &path) {
+                self.overall.inc(file.size.max(0) as u64);
+                self.println(format!(
+                    "Copied {} from the cache",
+                    style(&file.filename).cyan()
+                ));
+                return Ok(hashes);
+            }
+        }
+
+        // Files of local mods are copied, which works offline too
+        if let Some(from) = &file.local {
+            let hashes = copy(file, from, dir)?;
+
+            self.overall.inc(file.size.max(0) as u64);
+            self.println(format!("Copied {}", style(&file.filename).cyan()));
+
+            return Ok(hashes);
+        }
+
+        if self.client.is_offline() {
+            return Err(Error::Offline {
+                url: file.url.clone(),
+            });
+        }
+
+        let bar = self.multi.insert_before(
+            self.overall,
+            ProgressBar::new(file.size.max(0) as u64)
+                .with_style(bar_style(
+                    "{msg:32!} [{bar:40.cyan/blue}] {bytes}/{total_bytes}",
+                ))
+                .with_message(file.filename.clone()),
+        );
+
+        let progress = Progress {
+            bar: &bar,
+            overall: self.overall,
+        };
+
+        // Fall back to the file's other URLs, then the mirrors, once its own URL has failed for
+        // good
+        let mut urls = self.client.download_urls(&file.url);
+        urls.splice(1..1, file.mirrors.iter().cloned());
+        let mut result = self.retrying(file, &urls[0], dir, &progress);
+
+        for url in &urls[1..] {
+            let err = match &result {
+                Ok(_) => break,
+                Err(err) => err,
+            };
+
+            self.println(format!(
+                "{}: {}, trying {}",
+                style(&file.filename).cyan(),
+                err,
+                style(url).dim()
+            ));
+
+            result = self.retrying(file, url, dir, &progress);
+        }
+
+        bar.finish_and_clear();
+
+        let hashes = result?;
+
+        self.println(format!("Downloaded {}", style(&file.filename).cyan()));
+
+        if let Some(cache) = self.cache {
+            if let Err(err) = cache.insert(&hashes.sha512, &path) {
+                self.println(format!(
+                    "Failed to cache {}: {}",
+                    style(&file.filename).cyan(),
+                    err
+                ));
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    // Download a file from url, retrying transient failures with exponential backoff
+    fn retrying(&self, file: &File, url: &str, dir: &Path, progress: &Progress) -> Result<Hashes> {
+        let mut attempt = 0;
+
+        loop {
+            let err = match download(self.client, file, url, dir, &self.partial_dir, progress) {
+                Ok(hashes) => return Ok(hashes),
+                Err(err) => err,
+            };
+
+            if attempt >= self.config.retries || !transient(&err) {
+                return Err(err);
+            }
+
+            // retry_delay, then twice that, and so on
+            let delay =
+                Duration::from_millis(self.config.retry_delay) * 2u32.saturating_pow(attempt);
+            attempt += 1;
+
+            self.println(format!(
+                "{}: {}, retrying in {:.1}s ({}/{})",
+                style(&file.filename).cyan(),
+                err,
+                delay.as_secs_f32(),
+                attempt,
+                self.config.retries
+            ));
+
+            std::thread::sleep(delay);
+        }
+    }
+
+    // Print a message without tearing the progress bars
+    fn println(&self, msg: String) {
+        self.multi.suspend(|| crate::cinfoln(&msg));
+    }
+}
+
+// Where to keep unfinished downloads: the client's partial directory, or next to the target if
+// there is none or it can't be created
+fn partial_dir(partial_dir: Option<&Path>, dir: &Path) -> PathBuf {
+    partial_dir
+        .map(Path::to_path_buf)
+        .and_then(|partial| std::fs::create_dir_all(&partial).ok().map(|_| partial))
+        .unwrap_or_else(|| dir.to_path_buf())
+}
+
+// Errors that are worth trying again: dropped connections, timeouts, server errors and rate limits
+fn transient(err: &Error) -> bool {
+    match err {
+        Error::Network(_) | Error::Io(_) => true,
+        Error::Status { status, .. } => {
+            status.is_server_error()
+                || *status == StatusCode::TOO_MANY_REQUESTS
+                || *status == StatusCode::RANGE_NOT_SATISFIABLE
+        }
+        _ => false,
+    }
+}
+
+struct Progress<'a> {
+    bar: &'a ProgressBar,
+    overall: &'a ProgressBar,
+}
+
+impl Progress<'_> {
+    fn inc(&self, read: u64) {
+        self.bar.inc(read);
+        self.overall.inc(read);
+    }
+
+    // Start the file over, e.g. when the server ignored a range request
+    fn reset(&self) {
+        let position = self.bar.position();
+
+        self.bar.set_position(0);
+        self.overall
+            .set_position(self.overall.position().saturating_sub(position));
+    }
+}
+
+// Download a file from url into dir, hashing it as it arrives. Unfinished downloads are kept in
+// partial_dir and resumed with a range request if the file has a hash to check. The file is only
+// moved into place once it passes verify.
+fn download(
+    client: &ModrinthClient,
+    file: &File,
+    url: &str,
+    dir: &Path,
+    partial_dir: &Path,
+    progress: &Progress,
+) -> Result<Hashes> {
+    // Named after the file's sha512, or its URL until that is known
+    let partial = if file.hashes.sha512.is_empty() {
+        partial_dir.join(format!("{:x}.part", Sha512::digest(file.url.as_bytes())))
+    } else {
+        partial_dir.join(format!("{}.part", file.hashes.sha512))
+    };
+
+    // Only resume what can be checked once it's complete, anything else starts over
+    let checked = !file.hashes.sha512.is_empty() || !file.hashes.sha1.is_empty();
+
+    let offset = match partial.metadata() {
+        Ok(metadata) if checked => metadata.len(),
+        _ => 0,
+    };
+
+    let mut req = client.download(url);
+
+    if offset > 0 {
+        req = req.header(RANGE, format!("bytes={}-", offset));
+    }
+
+    let resp = req.send().map_err(Error::Network)?;
+
+    let resume = match resp.status() {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => true,
+        status if status.is_success() => false,
+        status => {
+            // The partial file doesn't fit the file on the server anymore, start over next time
+            if status == StatusCode::RANGE_NOT_SATISFIABLE {
+                let _ = std::fs::remove_file(&partial);
+            }
+
+            return Err(Error::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+    };
+
+    progress.reset();
+
+    if resume {
+        progress.inc(offset);
+    }
+
+    let hashes = stream(resp, &partial, resume, progress)?;
+
+    if let Err(err) = verify(file, &hashes) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(err);
+    }
+
+    place(&partial, &dir.join(&file.filename))?;
+
+    Ok(hashes)
+}
+
+// Check a file against every hash it should have, its sha512 and sha1 if known. Files with
+// neither, like GitHub release assets, are taken as they are and their hashes recorded.
+fn verify(file: &File, hashes: &Hashes) -> Result<()> {
+    let pairs = [
+        (&file.hashes.sha512, &hashes.sha512),
+        (&file.hashes.sha1, &hashes.sha1),
+    ];
+
+    for (expected, actual) in pairs {
+        if !expected.is_empty() && !expected.eq_ignore_ascii_case(actual) {
+            return Err(Error::HashMismatch {
+                file: file.filename.clone(),
+                expected: expected.clone(),
+                actual: actual.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Copy a file on disk into dir, checking it like a download
+fn copy(file: &File, from: &Path, dir: &Path) -> Result<Hashes> {
+    let hashes = crate::hash_file(from)?;
+
+    verify(file, &hashes)?;
+
+    let to = dir.join(&file.filename);
+    let tmp = to.with_file_name(format!(".{}.part", file.filename));
+
+    std::fs::copy(from, &tmp)?;
+    std::fs::rename(&tmp, &to)?;
+
+    Ok(hashes)
+}
+
+// Write body to path, appending to what's there if resume is set, and return the hashes of
+// the whole file
+fn stream(mut body: impl Read, path: &Path, resume: bool, progress: &Progress) -> Result<Hashes> {
+    let mut out = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+
+    let mut sha1 = Sha1::new();
+    let mut sha512 = Sha512::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    if resume {
+        loop {
+            let read = out.read(&mut buf)?;
+
+            if read == 0 {
+                break;
+            }
+
+            sha1.update(&buf[..read]);
+            sha512.update(&buf[..read]);
+        }
+    } else {
+        out.set_len(0)?;
+    }
+
+    loop {
+        let read = body.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        sha1.update(&buf[..read]);
+        sha512.update(&buf[..read]);
+        out.write_all(&buf[..read])?;
+
+        progress.inc(read as u64);
+    }
+
+    out.sync_all()?;
+
+    Ok(Hashes {
+        sha512: format!("{:x}", sha512.finalize()),
+        sha1: format!("{:x}", sha1.finalize()),
+    })
+}
+
+// Move a finished download to path. A plain rename is atomic, but the data directory may be on
+// another filesystem, in which case copy next to path first and rename from there.
+fn place(from: &Path, to: &Path) -> Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = to.with_file_name(format!(".{}.part", name));
+
+    std::fs::copy(from, &tmp)?;
+    std::fs::rename(&tmp, to)?;
+    std::fs::remove_file(from)?;
+
+    Ok(())
+}
+
+fn bar_style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .expect("valid progress bar template")
+        .progress_chars("=> ")
+}
@@ -0,0 +1,197 @@
+use std::{fmt::Display, path::PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // The request never got a response
+    Network(reqwest::Error),
+    // The server answered with a non-success status
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    // The response body wasn't what we expected
+    Decode {
+        url: String,
+        source: serde_json::Error,
+    },
+    // Offline and the response isn't cached
+    Offline {
+        url: String,
+    },
+    HashMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    // No version of the project supports the game version and loader
+    Unsupported {
+        project: String,
+        version: String,
+        loader: String,
+    },
+    // The project has no version matching a pinned version number or id
+    NoSuchVersion {
+        project: String,
+        requirement: String,
+    },
+    // A project id has a prefix that isn't a known source, e.g. foo:bar
+    UnknownSource {
+        name: String,
+    },
+    // A project id doesn't have the shape its source expects
+    BadProjectId {
+        id: String,
+        expected: String,
+    },
+    // The source needs an API key and none is configured
+    NoApiKey {
+        name: String,
+    },
+    // The source's files have to be pinned by sha512 and the requirement doesn't
+    NoHash {
+        project: String,
+    },
+    // The version has no files to download
+    NoFiles {
+        project: String,
+    },
+    // A file mdget reads or writes (manifest, lockfile, ...) is malformed
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+    // mdget's own state in the data directory is malformed
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    // A .mrpack or other archive couldn't be read
+    Zip {
+        path: PathBuf,
+        source: zip::result::ZipError,
+    },
+    // A file can only be verified with a hash mdget doesn't compute
+    UnsupportedHash {
+        file: String,
+        format: String,
+    },
+    // A pack lists a file that would be written outside of the instance
+    UnsafePath {
+        pack: String,
+        path: String,
+    },
+    // A pack lists a file without both a sha1 and a sha512
+    UnhashedFile {
+        pack: String,
+        path: String,
+    },
+    // A pack lists a download that isn't https or on one of the allowed hosts
+    UnsafeDownload {
+        pack: String,
+        url: String,
+    },
+    Io(std::io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Network(err) => write!(f, "network error: {}", err),
+            Error::Status { url, status } => write!(f, "{} returned {}", url, status),
+            Error::Decode { url, source } => {
+                write!(f, "unexpected response from {}: {}", url, source)
+            }
+            Error::Offline { url } => write!(f, "{} isn't cached, can't fetch it offline", url),
+            Error::HashMismatch {
+                file,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "hash mismatch for {}: expected {}, got {}",
+                file, expected, actual
+            ),
+            Error::Unsupported {
+                project,
+                version,
+                loader,
+            } => write!(
+                f,
+                "{} doesn't support version {} or loader {}",
+                project, version, loader
+            ),
+            Error::NoSuchVersion {
+                project,
+                requirement,
+            } => write!(f, "{} has no version {}", project, requirement),
+            Error::UnknownSource { name } => write!(f, "unknown mod source {}", name),
+            Error::BadProjectId { id, expected } => {
+                write!(f, "invalid project {}, expected {}", id, expected)
+            }
+            Error::NoApiKey { name } => write!(f, "no API key set for {}", name),
+            Error::NoHash { project } => write!(
+                f,
+                "{} has to be pinned with a sha512:<hex> requirement",
+                project
+            ),
+            Error::NoFiles { project } => write!(f, "{} has no files to download", project),
+            Error::Parse { path, source } => {
+                write!(f, "failed to parse {}: {}", path.display(), source)
+            }
+            Error::Serialize(err) => write!(f, "failed to serialize: {}", err),
+            Error::Json { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Zip { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::UnsupportedHash { file, format } => {
+                write!(f, "can't verify {} with a {} hash", file, format)
+            }
+            Error::UnsafePath { pack, path } => {
+                write!(
+                    f,
+                    "{} contains a file outside of the instance: {}",
+                    pack, path
+                )
+            }
+            Error::UnhashedFile { pack, path } => {
+                write!(f, "{} lists {} without a sha1 and sha512", pack, path)
+            }
+            Error::UnsafeDownload { pack, url } => {
+                write!(
+                    f,
+                    "{} downloads a file from a host packs can't use: {}",
+                    pack, url
+                )
+            }
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(err) => Some(err),
+            Error::Decode { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            Error::Serialize(err) => Some(err),
+            Error::Json { source, .. } => Some(source),
+            Error::Zip { source, .. } => Some(source),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Error::Serialize(err)
+    }
+}
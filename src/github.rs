@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    source::ModSource, Error, File, ModrinthClient, ModrinthProject, ProjectVersion,
+    ProjectVersions, Result,
+};
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+// Release assets of a repository, e.g. github:owner/repo for the jars of each release, or
+// github:owner/repo/mymod-fabric-*.jar for the assets matching a pattern with * and ?
+pub const GITHUB: &str = "github";
+
+// The most releases GitHub lists at once, older ones can still be pinned by tag
+const PER_PAGE: u32 = 100;
+
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    client: ModrinthClient,
+    api_url: String,
+    // Raises the rate limit from 60 requests an hour and gives access to private repositories.
+    // Their assets are downloaded through the API, which needs the download_headers.
+    token: Option<String>,
+    // owner/repo -> whether the repository is private, remembered from the repositories
+    // fetched so far
+    private: Arc<Mutex<HashMap<String, bool>>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Repository {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub private: bool,
+    pub description: Option<String>,
+    pub html_url: String,
+    pub stargazers_count: i64,
+    pub topics: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Asset {
+    pub id: u64,
+    // The asset in the API, which serves the file to requests accepting application/octet-stream
+    pub url: String,
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: i64,
+}
+
+// Headers for downloading the assets of private repositories through the API
+pub fn download_headers(token: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+
+    if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    headers
+}
+
+// owner, repo and the asset pattern of a project id
+// Percent-encode s for use as a single segment of a URL path, e.g. a tag like mymod/1.0+fabric
+fn path_segment(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn parse_id(id: &str) -> Result<(&str, &str, Option<&str>)> {
+    let mut parts = id.splitn(3, '/');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(owner), Some(repo), pattern) if !owner.is_empty() && !repo.is_empty() => {
+            Ok((owner, repo, pattern.filter(|pattern| !pattern.is_empty())))
+        }
+        _ => Err(Error::BadProjectId {
+            id: format!("{}:{}", GITHUB, id),
+            expected: "github:owner/repo or github:owner/repo/<asset pattern>".to_string(),
+        }),
+    }
+}
+
+// Whether name matches a pattern where * is any run of characters and ? any one character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // Where the last * was and how much of name it covered then
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the * take one more character
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Assets of a release that are mods: the ones matching the pattern, or the jars that aren't
+// sources or dev builds
+fn wanted(asset: &Asset, pattern: Option<&str>) -> bool {
+    match pattern {
+        Some(pattern) => glob_match(pattern, &asset.name),
+        None => {
+            asset.name.ends_with(".jar")
+                && !asset.name.ends_with("-sources.jar")
+                && !asset.name.ends_with("-dev.jar")
+        }
+    }
+}
+
+impl Release {
+    // The assets of private repositories can only be downloaded through the API
+    pub fn version(
+        &self,
+        project_id: &str,
+        pattern: Option<&str>,
+        private: bool,
+    ) -> ProjectVersion {
+        let files = self
+            .assets
+            .iter()
+            .filter(|asset| wanted(asset, pattern))
+            .enumerate()
+            .map(|(i, asset)| File {
+                url: if private {
+                    asset.url.clone()
+                } else {
+                    asset.browser_download_url.clone()
+                },
+                filename: asset.name.clone(),
+                primary: i == 0,
+                size: asset.size,
+                ..Default::default()
+            })
+            .collect();
+
+        ProjectVersion {
+            name: self.name.clone(),
+            version_number: Some(self.tag_name.clone()),
+            changelog: self.body.clone(),
+            version_type: Some(if self.prerelease { "beta" } else { "release" }.to_string()),
+            id: Some(self.tag_name.clone()),
+            project_id: Some(project_id.to_string()),
+            date_published: self.published_at.clone(),
+            files,
+            ..Default::default()
+        }
+    }
+}
+
+impl GitHubClient {
+    pub fn new(client: ModrinthClient, token: Option<String>) -> Self {
+        GitHubClient {
+            client,
+            api_url: GITHUB_API_URL.to_string(),
+            token,
+            private: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Talk to a GitHub Enterprise server, e.g. https://github.example.com/api/v3
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn repository(&self, owner: &str, repo: &str) -> Result<Repository> {
+        let repository: Repository = self.get(&format!("/repos/{}/{}", owner, repo))?;
+
+        self.private
+            .lock()
+            .unwrap()
+            .insert(format!("{}/{}", owner, repo), repository.private);
+
+        Ok(repository)
+    }
+
+    // Newest first
+    pub fn releases(&self, owner: &str, repo: &str) -> Result<Vec<Release>> {
+        self.get(&format!(
+            "/repos/{}/{}/releases?per_page={}",
+            owner, repo, PER_PAGE
+        ))
+    }
+
+    pub fn release(&self, owner: &str, repo: &str, tag: &str) -> Result<Release> {
+        self.get(&format!(
+            "/repos/{}/{}/releases/tags/{}",
+            owner,
+            repo,
+            path_segment(tag)
+        ))
+    }
+
+    // Whether a repository is private, which it can only be if there is a token to see it with.
+    // The repository is only fetched if it wasn't already, e.g. for its project.
+    fn private(&self, owner: &str, repo: &str) -> Result<bool> {
+        if self.token.is_none() {
+            return Ok(false);
+        }
+
+        let known = self
+            .private
+            .lock()
+            .unwrap()
+            .get(&format!("{}/{}", owner, repo))
+            .copied();
+
+        match known {
+            Some(private) => Ok(private),
+            None => Ok(self.repository(owner, repo)?.private),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+
+        if let Some(token) = &self.token {
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+                headers.insert(AUTHORIZATION, value);
+            }
+        }
+
+        self.client
+            .fetch_with_headers(&format!("{}{}", self.api_url, path), &headers)
+    }
+}
+
+impl ModSource for GitHubClient {
+    fn name(&self) -> &str {
+        GITHUB
+    }
+
+    fn project(&self, id: &str) -> Result<ModrinthProject> {
+        let (owner, repo, _) = parse_id(id)?;
+        let repository = self.repository(owner, repo)?;
+
+        Ok(ModrinthProject {
+            // The pattern is part of the id, it decides which files the project has
+            id: format!("{}:{}", GITHUB, id),
+            slug: repository.name.clone(),
+            project_type: "mod".to_string(),
+            title: repository.full_name.clone(),
+            description: repository.description.clone().unwrap_or_default(),
+            status: "approved".to_string(),
+            client_side: "unknown".to_string(),
+            server_side: "unknown".to_string(),
+            followers: repository.stargazers_count,
+            categories: repository.topics.clone(),
+            issues_url: Some(format!("{}/issues", repository.html_url)),
+            source_url: Some(repository.html_url),
+            ..Default::default()
+        })
+    }
+
+    // Releases with a matching asset, drafts aside
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        let (owner, repo, pattern) = parse_id(id)?;
+        let project_id = format!("{}:{}", GITHUB, id);
+        let private = self.private(owner, repo)?;
+
+        Ok(self
+            .releases(owner, repo)?
+            .iter()
+            .filter(|release| !release.draft)
+            .map(|release| release.version(&project_id, pattern, private))
+            .filter(|version| !version.files.is_empty())
+            .collect())
+    }
+
+    // Versions are release tags
+    fn version(&self, project: &str, id: &str) -> Result<ProjectVersion> {
+        let (owner, repo, pattern) = parse_id(project)?;
+        let private = self.private(owner, repo)?;
+
+        Ok(self.release(owner, repo, id)?.version(
+            &format!("{}:{}", GITHUB, project),
+            pattern,
+            private,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("mymod-*.jar", "mymod-1.2.3.jar"));
+        assert!(glob_match(
+            "mymod-*-fabric.jar",
+            "mymod-1.2-beta-fabric.jar"
+        ));
+        assert!(glob_match("mymod-?.jar", "mymod-1.jar"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**.jar", "a.jar"));
+
+        assert!(!glob_match("mymod-?.jar", "mymod-12.jar"));
+        assert!(!glob_match("mymod-*.jar", "mymod-1.2.3-sources.zip"));
+        assert!(!glob_match("*-fabric.jar", "mymod-forge.jar"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn release_version_picks_wanted_assets() {
+        let asset = |name: &str| Asset {
+            id: 1,
+            url: format!("https://api.github.com/repos/o/r/releases/assets/{}", name),
+            name: name.to_string(),
+            browser_download_url: format!("https://github.com/o/r/releases/download/v1/{}", name),
+            size: 10,
+        };
+
+        let release = Release {
+            tag_name: "v1".to_string(),
+            assets: vec![
+                asset("mymod-1.0-sources.jar"),
+                asset("mymod-1.0.jar"),
+                asset("mymod-1.0-dev.jar"),
+            ],
+            ..Default::default()
+        };
+
+        let version = release.version("github:o/r", None, false);
+
+        assert_eq!(version.id.as_deref(), Some("v1"));
+        assert_eq!(version.files.len(), 1);
+        assert_eq!(version.files[0].filename, "mymod-1.0.jar");
+        assert!(version.files[0].primary);
+        assert!(version.files[0].url.starts_with("https://github.com/"));
+
+        let private = release.version("github:o/r", Some("*-dev.jar"), true);
+
+        assert_eq!(private.files.len(), 1);
+        assert!(private.files[0].url.starts_with("https://api.github.com/"));
+    }
+
+    #[test]
+    fn tags_are_encoded_as_one_path_segment() {
+        assert_eq!(path_segment("v1.2.3"), "v1.2.3");
+        assert_eq!(path_segment("mymod/1.0+fabric"), "mymod%2F1.0%2Bfabric");
+        assert_eq!(path_segment("a b?#"), "a%20b%3F%23");
+    }
+
+    #[test]
+    fn parse_id_with_and_without_pattern() {
+        assert_eq!(parse_id("o/r").unwrap(), ("o", "r", None));
+        assert_eq!(parse_id("o/r/").unwrap(), ("o", "r", None));
+        assert_eq!(parse_id("o/r/*.jar").unwrap(), ("o", "r", Some("*.jar")));
+        assert!(parse_id("o").is_err());
+        assert!(parse_id("/r").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Directory in the data directory holding cached API responses
+pub const HTTP_CACHE_DIR: &str = "http";
+
+// On-disk cache of GET responses, revalidated with ETag / Last-Modified once they are older
+// than the TTL
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // Unix timestamp of when the response was last fetched or revalidated
+    pub fetched_at: i64,
+    pub body: String,
+}
+
+impl HttpCache {
+    pub fn open(data_dir: &Path, ttl: Duration) -> std::io::Result<Self> {
+        let dir = data_dir.join(HTTP_CACHE_DIR);
+
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(HttpCache { dir, ttl })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let content = std::fs::read_to_string(self.path(url)).ok()?;
+
+        serde_json::from_str::<CachedResponse>(&content)
+            .ok()
+            .filter(|cached| cached.url == url)
+    }
+
+    // Whether a response is recent enough to use without asking the server
+    pub fn is_fresh(&self, cached: &CachedResponse) -> bool {
+        let age = chrono::Utc::now().timestamp() - cached.fetched_at;
+
+        age >= 0 && (age as u64) < self.ttl.as_secs()
+    }
+
+    // Store a response. Failing to write the cache isn't worth failing a request over,
+    // so errors are ignored.
+    pub fn put(&self, cached: &CachedResponse) {
+        let json = match serde_json::to_string(cached) {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+
+        let path = self.path(&cached.url);
+        let tmp = path.with_extension(format!("{:?}.tmp", std::thread::current().id()));
+
+        if std::fs::write(&tmp, json).is_ok() && std::fs::rename(&tmp, &path).is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+    }
+}
@@ -1,18 +1,170 @@
 use serde_json::Value;
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    io::Read,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use console::{style, Color};
+use directories::ProjectDirs;
 use serde_derive::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
 
-pub const USER_AGENT: &'static str = "kalkafox/mdget/0.1.0";
+pub mod cache;
+pub mod client;
+pub mod curseforge;
+pub mod direct;
+pub mod download;
+pub mod error;
+pub mod github;
+pub mod http_cache;
+pub mod lock;
+pub mod manifest;
+pub mod pack;
+pub mod packwiz;
+pub mod pool;
+pub mod resolve;
+pub mod source;
+pub mod state;
 
-pub const API_URL: &'static str = "https://api.modrinth.com/v2";
+pub use client::{ModrinthClient, SearchQuery};
+pub use curseforge::CurseForgeClient;
+pub use direct::{LocalSource, UrlSource};
+pub use error::{Error, Result};
+pub use github::GitHubClient;
+pub use source::{ModSource, Sources};
+
+pub const USER_AGENT: &str = "kalkafox/mdget/0.1.0";
+
+pub const API_URL: &str = "https://api.modrinth.com/v2";
+
+pub const VERSION_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
 
 // Implement display and ToString here
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     pub version: String,
     pub loader: String,
+    // How many downloads and API requests may run at once
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    // How many times to retry a download after a dropped connection, 5xx or 429
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    // Milliseconds to wait before the first retry, doubled after every attempt
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: u64,
+    // Size limit of the download cache in MiB, least recently used files go first
+    #[serde(default = "default_cache_size")]
+    pub cache_size: u64,
+    // Hard link files out of the cache instead of copying them
+    #[serde(default = "default_cache_link")]
+    pub cache_link: bool,
+    // Seconds before cached API responses are checked with the server again
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    // Modrinth-compatible API to use instead of api.modrinth.com
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    #[serde(default = "default_version_manifest_url")]
+    pub version_manifest_url: String,
+    // Hosts tried in order when a file can't be downloaded from its own URL. The file's
+    // path is kept, e.g. https://cdn.modrinth.com/data/... -> https://mirror.example/data/...
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    // Modrinth personal access token. Can also be set with MODRINTH_TOKEN or a token file
+    // next to the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    // Version of the loader itself, e.g. 0.14.21 for fabric. Needed to export packs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    // Directories put into exported packs as overrides
+    #[serde(default = "default_pack_overrides")]
+    pub pack_overrides: Vec<String>,
+    #[serde(default = "default_curseforge_api_url")]
+    pub curseforge_api_url: String,
+    // Needed to install curseforge: mods. Can also be set with CURSEFORGE_API_KEY.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge_api_key: Option<String>,
+    #[serde(default = "default_github_api_url")]
+    pub github_api_url: String,
+    // For github: mods in private repositories or past the rate limit. Can also be set with
+    // GITHUB_TOKEN.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+}
+
+fn default_concurrency() -> usize {
+    8
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay() -> u64 {
+    500
+}
+
+fn default_cache_size() -> u64 {
+    2048
+}
+
+fn default_cache_link() -> bool {
+    true
+}
+
+fn default_cache_ttl() -> u64 {
+    600
+}
+
+fn default_pack_overrides() -> Vec<String> {
+    vec!["config".to_string()]
+}
+
+fn default_api_url() -> String {
+    API_URL.to_string()
+}
+
+fn default_version_manifest_url() -> String {
+    VERSION_MANIFEST_URL.to_string()
+}
+
+fn default_curseforge_api_url() -> String {
+    curseforge::CURSEFORGE_API_URL.to_string()
+}
+
+fn default_github_api_url() -> String {
+    github::GITHUB_API_URL.to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: "1.16.5".to_string(),
+            loader: "fabric".to_string(),
+            concurrency: default_concurrency(),
+            retries: default_retries(),
+            retry_delay: default_retry_delay(),
+            cache_size: default_cache_size(),
+            cache_link: default_cache_link(),
+            cache_ttl: default_cache_ttl(),
+            api_url: default_api_url(),
+            version_manifest_url: default_version_manifest_url(),
+            mirrors: Vec::new(),
+            token: None,
+            loader_version: None,
+            pack_overrides: default_pack_overrides(),
+            curseforge_api_url: default_curseforge_api_url(),
+            curseforge_api_key: None,
+            github_api_url: default_github_api_url(),
+            github_token: None,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -63,7 +215,7 @@ pub struct ModrinthProject {
     pub body_url: Value,
     pub published: String,
     pub updated: String,
-    pub approved: String,
+    pub approved: Option<String>,
     pub queued: Value,
     pub status: String,
     #[serde(rename = "requested_status")]
@@ -85,15 +237,15 @@ pub struct ModrinthProject {
     pub loaders: Vec<String>,
     pub versions: Vec<String>,
     #[serde(rename = "icon_url")]
-    pub icon_url: String,
+    pub icon_url: Option<String>,
     #[serde(rename = "issues_url")]
-    pub issues_url: String,
+    pub issues_url: Option<String>,
     #[serde(rename = "source_url")]
-    pub source_url: String,
+    pub source_url: Option<String>,
     #[serde(rename = "wiki_url")]
     pub wiki_url: Value,
     #[serde(rename = "discord_url")]
-    pub discord_url: String,
+    pub discord_url: Option<String>,
     #[serde(rename = "donation_urls")]
     pub donation_urls: Vec<DonationUrl>,
     pub gallery: Vec<Value>,
@@ -101,7 +253,7 @@ pub struct ModrinthProject {
     pub flame_anvil_project: Value,
     #[serde(rename = "flame_anvil_user")]
     pub flame_anvil_user: Value,
-    pub color: i64,
+    pub color: Option<i64>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -240,6 +392,12 @@ pub struct File {
     pub size: i64,
     #[serde(rename = "file_type")]
     pub file_type: Option<String>,
+    // Other URLs of the same file to try after url, e.g. the rest of a .mrpack file's downloads
+    #[serde(skip)]
+    pub mirrors: Vec<String>,
+    // A jar on disk to copy instead of downloading url, set for files of local mods
+    #[serde(skip)]
+    pub local: Option<PathBuf>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -249,6 +407,17 @@ pub struct Hashes {
     pub sha1: String,
 }
 
+impl Hashes {
+    // Compare by sha512, or by sha1 if either side only knows that one
+    pub fn matches(&self, other: &Hashes) -> bool {
+        if !self.sha512.is_empty() && !other.sha512.is_empty() {
+            self.sha512.eq_ignore_ascii_case(&other.sha512)
+        } else {
+            !self.sha1.is_empty() && self.sha1.eq_ignore_ascii_case(&other.sha1)
+        }
+    }
+}
+
 pub type ProjectVersions = Vec<ProjectVersion>;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -281,6 +450,109 @@ pub struct ProjectVersion {
     pub files: Vec<File>,
 }
 
+impl ProjectVersion {
+    // Whether this version can be installed for the game version and loader. Versions that
+    // don't say, like direct downloads and GitHub releases, are taken to support any.
+    pub fn supports(&self, version: &str, loader: &str) -> bool {
+        (self.game_versions.is_empty() || self.game_versions.iter().any(|v| v == version))
+            && (self.loaders.is_empty() || self.loaders.iter().any(|l| l == loader))
+    }
+
+    // The file marked as primary, falling back to the first one
+    pub fn primary_file(&self) -> Option<&File> {
+        self.files
+            .iter()
+            .find(|f| f.primary)
+            .or_else(|| self.files.first())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub offset: i64,
+    pub limit: i64,
+    #[serde(rename = "total_hits")]
+    pub total_hits: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchHit {
+    #[serde(rename = "project_id")]
+    pub project_id: String,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    #[serde(rename = "project_type")]
+    pub project_type: String,
+    pub categories: Vec<String>,
+    #[serde(rename = "client_side")]
+    pub client_side: String,
+    #[serde(rename = "server_side")]
+    pub server_side: String,
+    pub versions: Vec<String>,
+    pub downloads: i64,
+    pub follows: i64,
+    #[serde(rename = "date_modified")]
+    pub date_modified: String,
+    #[serde(rename = "latest_version")]
+    pub latest_version: Option<String>,
+    pub license: String,
+}
+
+// Hex encoded sha512 of a file on disk
+pub fn sha512_file(path: &std::path::Path) -> std::io::Result<String> {
+    let mut hasher = Sha512::new();
+    let mut file = std::fs::File::open(path)?;
+
+    std::io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Both hashes of a file on disk, as Modrinth reports them
+pub fn hash_file(path: &std::path::Path) -> std::io::Result<Hashes> {
+    let mut sha1 = Sha1::new();
+    let mut sha512 = Sha512::new();
+    let mut file = std::fs::File::open(path)?;
+    let mut buf = [0; 8192];
+
+    loop {
+        let read = file.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        sha1.update(&buf[..read]);
+        sha512.update(&buf[..read]);
+    }
+
+    Ok(Hashes {
+        sha512: format!("{:x}", sha512.finalize()),
+        sha1: format!("{:x}", sha1.finalize()),
+    })
+}
+
+// Where mdget keeps its config and data, e.g. ~/.config/mdget and ~/.local/share/mdget
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("dev", "kalkafox", "mdget")
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Silence info and warning messages, e.g. when printing JSON
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
 // Helper function for printing colored messages
 fn print_colored_message(msg_type: &str, msg: &str, color: Color) {
     // Get the current time
@@ -297,22 +569,34 @@ fn print_colored_message(msg_type: &str, msg: &str, color: Color) {
 
 // colored info
 pub fn cinfo(msg: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
     print_colored_message("  ", msg, Color::Green);
 }
 
 // colored info with newline
 pub fn cinfoln(msg: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
     cinfo(msg);
     println!();
 }
 
 // colored warning
 pub fn cwarn(msg: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
     print_colored_message("  ", msg, Color::Yellow);
 }
 
 // colored warning with newline
 pub fn cwarnln(msg: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
     cwarn(msg);
     println!();
 }
@@ -327,3 +611,43 @@ pub fn cerrorln(msg: &str) {
     cerror(msg);
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(sha512: &str, sha1: &str) -> Hashes {
+        Hashes {
+            sha512: sha512.to_string(),
+            sha1: sha1.to_string(),
+        }
+    }
+
+    #[test]
+    fn hashes_match_by_sha512_first() {
+        assert!(hashes("AB", "1").matches(&hashes("ab", "2")));
+        assert!(!hashes("ab", "1").matches(&hashes("cd", "1")));
+    }
+
+    #[test]
+    fn hashes_fall_back_to_sha1() {
+        assert!(hashes("", "Ab").matches(&hashes("cd", "aB")));
+        assert!(hashes("ab", "1").matches(&hashes("", "1")));
+        assert!(!hashes("", "1").matches(&hashes("", "2")));
+        assert!(!hashes("", "").matches(&hashes("", "")));
+    }
+
+    #[test]
+    fn versions_without_game_versions_or_loaders_support_any() {
+        let version = ProjectVersion {
+            game_versions: vec!["1.20.1".to_string()],
+            loaders: vec!["fabric".to_string()],
+            ..Default::default()
+        };
+
+        assert!(version.supports("1.20.1", "fabric"));
+        assert!(!version.supports("1.20.1", "forge"));
+        assert!(!version.supports("1.19.4", "fabric"));
+        assert!(ProjectVersion::default().supports("1.19.4", "forge"));
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    manifest::Manifest, resolve::Resolved, Error, File, Hashes, ModrinthProject, ProjectVersion,
+    Result,
+};
+
+pub const LOCK_FILE: &str = "mdget.lock";
+
+// Exact resolution of a manifest (mdget.lock), including dependencies
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: String,
+    pub loader: String,
+    // The manifest's mods table this lockfile was resolved from
+    #[serde(default)]
+    pub requested: BTreeMap<String, String>,
+    #[serde(default, rename = "mod")]
+    pub mods: Vec<LockedMod>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedMod {
+    pub slug: String,
+    pub project_id: String,
+    pub version_id: String,
+    pub version_number: String,
+    pub filename: String,
+    pub url: String,
+    pub size: i64,
+    // false if the mod was only pulled in as a dependency
+    #[serde(default)]
+    pub requested: bool,
+    // Project ids of the locked mods that depend on this one
+    #[serde(default)]
+    pub required_by: Vec<String>,
+    pub hashes: Hashes,
+}
+
+impl LockedMod {
+    // Lock the primary file of a resolved version
+    pub fn new(resolved: &Resolved) -> Option<Self> {
+        let file = resolved.version.primary_file()?;
+
+        Some(LockedMod {
+            requested: resolved.requested,
+            required_by: resolved.required_by.clone(),
+            ..Self::for_file(&resolved.project, &resolved.version, file)
+        })
+    }
+
+    // Lock a specific file of a version, as if it was requested
+    pub fn for_file(project: &ModrinthProject, version: &ProjectVersion, file: &File) -> Self {
+        LockedMod {
+            slug: project.slug.clone(),
+            project_id: project.id.clone(),
+            version_id: version.id.clone().unwrap_or_default(),
+            version_number: version.version_number.clone().unwrap_or_default(),
+            filename: file.filename.clone(),
+            url: file.url.clone(),
+            size: file.size,
+            requested: true,
+            required_by: Vec::new(),
+            hashes: file.hashes.clone(),
+        }
+    }
+
+    // The locked file in the shape the API returns it
+    pub fn file(&self) -> File {
+        File {
+            hashes: self.hashes.clone(),
+            url: self.url.clone(),
+            filename: self.filename.clone(),
+            primary: true,
+            size: self.size,
+            file_type: None,
+            mirrors: Vec::new(),
+            // Local mods are copied from the jar their id points at
+            local: self
+                .project_id
+                .strip_prefix(crate::direct::LOCAL)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .map(PathBuf::from),
+        }
+    }
+}
+
+impl Lockfile {
+    pub fn new(manifest: &Manifest) -> Self {
+        Lockfile {
+            version: manifest.version.clone(),
+            loader: manifest.loader.clone(),
+            requested: manifest.mods.clone(),
+            mods: Vec::new(),
+        }
+    }
+
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(LOCK_FILE)
+    }
+
+    // Returns Ok(None) if the directory has no lockfile
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(dir);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+
+        match toml::from_str::<Lockfile>(&content) {
+            Ok(data) => Ok(Some(data)),
+            Err(source) => Err(Error::Parse { path, source }),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let toml = toml::to_string(self)?;
+
+        std::fs::write(Self::path(dir), toml)?;
+
+        Ok(())
+    }
+
+    // Whether the lockfile still describes the manifest
+    pub fn matches(&self, manifest: &Manifest) -> bool {
+        self.version == manifest.version
+            && self.loader == manifest.loader
+            && self.requested == manifest.mods
+    }
+
+    // Locked version id for a project, looked up by slug or id
+    pub fn locked_version(&self, project: &str) -> Option<&str> {
+        self.mods
+            .iter()
+            .find(|m| m.slug == project || m.project_id == project)
+            .map(|m| m.version_id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_mods_are_copied_from_disk() {
+        let local = LockedMod {
+            project_id: "file:libs/mymod.jar".to_string(),
+            url: "file:libs/mymod.jar".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(local.file().local, Some(PathBuf::from("libs/mymod.jar")));
+
+        // A file: URL alone doesn't make a mod local
+        let remote = LockedMod {
+            project_id: "AANobbMI".to_string(),
+            url: "file:/etc/passwd".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(remote.file().local, None);
+    }
+}
@@ -1,22 +1,60 @@
 use std::{
-    io::{Bytes, Write},
-    path::Path,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, HashSet},
+    io::Write,
+    path::{Component, Path, PathBuf},
+    time::Duration,
 };
 
-use console::style;
-use directories::ProjectDirs;
+use console::{style, Term};
+use indicatif::HumanBytes;
 use mdget::{
-    Config, Dependencies, File, MinecraftVersions, ModrinthProject, ProjectVersion, ProjectVersions,
+    cache::Cache,
+    download::{download_all, PARTIAL_DIR},
+    http_cache::HttpCache,
+    lock::{LockedMod, Lockfile},
+    manifest::{Manifest, LATEST},
+    pack::{Pack, PackEnv, PackFile, PackIndex, PackWriter},
+    packwiz::{
+        CurseForgeUpdate, IndexEntry, IndexRef, IndexToml, ModDownload, ModToml, ModUpdate,
+        ModrinthUpdate, PackToml, METAFILE_EXTENSION, PACK_FILE,
+    },
+    resolve::{Conflict, Resolution, Resolver},
+    state::{Install, State},
+    Config, CurseForgeClient, File, GitHubClient, LocalSource, MinecraftVersions, ModrinthClient,
+    ModrinthProject, ProjectVersion, SearchQuery, Sources, UrlSource,
 };
-use serde_json::Value;
-use sha2::{Digest, Sha256, Sha512};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+// File in the config directory holding a Modrinth personal access token
+const TOKEN_FILE: &str = "token";
+
+// Projects that failed to install and why
+type Failures = Vec<(String, mdget::Error)>;
+
+fn main() {
+    if let Err(err) = run() {
+        mdget::cerrorln(&err.to_string());
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().collect::<Vec<String>>();
+
+    // Global flags can go anywhere, take them out before looking at the command
+    let offline = take_flag(&mut args, "--offline");
+    let api_url = take_options(&mut args, "--api-url").pop();
+    let version_manifest_url = take_options(&mut args, "--version-manifest-url").pop();
+    let mirrors = take_options(&mut args, "--mirror");
+
+    // Keep stdout machine readable
+    if flag(&args, "--json") {
+        mdget::set_quiet(true);
+    }
+
     let mut config_path: std::path::PathBuf;
-    let mut data_dir: std::path::PathBuf;
+    let data_dir: std::path::PathBuf;
 
-    if let Some(proj_dirs) = ProjectDirs::from("dev", "kalkafox", "mdget") {
+    if let Some(proj_dirs) = mdget::project_dirs() {
         config_path = proj_dirs.config_dir().to_path_buf();
         data_dir = proj_dirs.data_dir().to_path_buf();
     } else {
@@ -32,39 +70,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if !data_dir.exists() {
         std::fs::create_dir_all(&data_dir)?;
     }
-    // First thing to do: Check if we're connected to the internet!
-    mdget::cinfoln(" Getting Minecraft versions from API...");
-
-    // timeout after 5 seconds
-    let timeout = std::time::Duration::from_secs(5);
-
-    let mut headers = reqwest::header::HeaderMap::new();
-
-    // User-Agent: kalkafox/mdget/0.1.0
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static(mdget::USER_AGENT),
-    );
-
-    let client = reqwest::blocking::Client::builder()
-        .timeout(timeout)
-        .default_headers(headers.clone())
-        .build()?;
-    let resp = client
-        .get("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json")
-        .send();
-
-    match resp {
-        Ok(_) => {
-            mdget::cinfoln(" Done!");
-        }
-        Err(_) => {
-            mdget::cerrorln("Failed to connect to the internet! Make sure you're connected :(");
-            return Ok(());
-        }
-    }
 
-    let resp = resp?.json::<MinecraftVersions>()?;
+    let mut state = State::load(&data_dir)?;
 
     // Read the config file
     config_path.push("config.toml");
@@ -74,6 +81,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let config: Config = Config {
             version: "1.16.5".to_string(),
             loader: "fabric".to_string(),
+            ..Default::default()
         };
 
         // serialize the config to toml
@@ -98,6 +106,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let mut config: Config = Config {
                 version: "1.16.5".to_string(),
                 loader: "fabric".to_string(),
+                ..Default::default()
             };
 
             // attempt to read the old config file
@@ -127,11 +136,90 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     mdget::cinfoln("Hello, world!");
 
-    let args = std::env::args().collect::<Vec<String>>();
+    // Command line options win over environment variables, which win over the config
+    let api_url = api_url
+        .or_else(|| std::env::var("MDGET_API_URL").ok())
+        .unwrap_or_else(|| config.api_url.clone());
+
+    let version_manifest_url = version_manifest_url
+        .or_else(|| std::env::var("MDGET_VERSION_MANIFEST_URL").ok())
+        .unwrap_or_else(|| config.version_manifest_url.clone());
+
+    let mirrors = match (mirrors, std::env::var("MDGET_MIRRORS")) {
+        (mirrors, _) if !mirrors.is_empty() => mirrors,
+        // Comma separated
+        (_, Ok(env)) => env
+            .split(',')
+            .map(|mirror| mirror.trim().to_string())
+            .filter(|mirror| !mirror.is_empty())
+            .collect(),
+        _ => config.mirrors.clone(),
+    };
+
+    // MODRINTH_TOKEN, then the config, then the token file in the config directory
+    let token = std::env::var("MODRINTH_TOKEN")
+        .ok()
+        .or_else(|| config.token.clone())
+        .or_else(|| std::fs::read_to_string(config_path.with_file_name(TOKEN_FILE)).ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+
+    let client = ModrinthClient::new()?
+        .api_url(&api_url)
+        .token(token)
+        .version_manifest_url(&version_manifest_url)
+        .mirrors(mirrors)
+        .with_cache(HttpCache::open(
+            &data_dir,
+            Duration::from_secs(config.cache_ttl),
+        )?)
+        .with_download_cache(Cache::open(&data_dir, config.cache_link)?)
+        .with_partial_dir(data_dir.join(PARTIAL_DIR))
+        .offline(offline);
+
+    // CURSEFORGE_API_KEY, then the config
+    let curseforge_api_key = std::env::var("CURSEFORGE_API_KEY")
+        .ok()
+        .or_else(|| config.curseforge_api_key.clone())
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty());
+
+    let curseforge_api_url = std::env::var("MDGET_CURSEFORGE_API_URL")
+        .unwrap_or_else(|_| config.curseforge_api_url.clone());
+
+    // GITHUB_TOKEN, then the config
+    let github_token = std::env::var("GITHUB_TOKEN")
+        .ok()
+        .or_else(|| config.github_token.clone())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+
+    let github_api_url =
+        std::env::var("MDGET_GITHUB_API_URL").unwrap_or_else(|_| config.github_api_url.clone());
+
+    // Assets of private repositories are downloaded from the API with the token
+    let client = match &github_token {
+        Some(token) => client.with_download_headers(
+            &format!("{}/", github_api_url.trim_end_matches('/')),
+            mdget::github::download_headers(token),
+        ),
+        None => client,
+    };
+
+    let sources = Sources::new(client.clone())
+        .with(
+            CurseForgeClient::new(client.clone(), curseforge_api_key).api_url(&curseforge_api_url),
+        )
+        .with(GitHubClient::new(client.clone(), github_token).api_url(&github_api_url))
+        .with(UrlSource)
+        .with(LocalSource);
+
+    if offline {
+        mdget::cwarnln("Offline, only using cached data");
+    }
 
     if args.len() < 2 {
-        mdget::cinfoln("Usage: mdget <command> [args]");
-        return Ok(());
+        return Err("Usage: mdget <command> [args]".into());
     }
 
     match args[1].as_str() {
@@ -142,17 +230,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             match args[2].as_str() {
                 "set" => {
-                    if args.len() < 3 {
-                        mdget::cerrorln("Usage: mdget version set <version>");
-                        return Ok(());
+                    if args.len() < 4 {
+                        return Err("Usage: mdget version set <version>".into());
                     }
 
                     // Check if the version is valid
-                    let version = resp.versions.iter().find(|v| v.id == args[3]);
+                    let versions = minecraft_versions(&client)?;
+                    let version = versions.versions.iter().find(|v| v.id == args[3]);
 
                     if version.is_none() {
-                        mdget::cerrorln(format!("Invalid version {}!", args[3]).as_str());
-                        return Ok(());
+                        return Err(format!("Invalid version {}!", args[3]).into());
                     }
 
                     let version = version.unwrap();
@@ -168,14 +255,49 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 _ => {}
             }
         }
+        "init" => {
+            let dir = std::env::current_dir()?;
+
+            if Manifest::path(&dir).exists() {
+                return Err(format!("{} already exists!", mdget::manifest::MANIFEST_FILE).into());
+            }
+
+            let mut manifest = Manifest::new(&config);
+
+            // mdget init [version] [loader]
+            if let Some(version) = args.get(2) {
+                if !minecraft_versions(&client)?
+                    .versions
+                    .iter()
+                    .any(|v| &v.id == version)
+                {
+                    return Err(format!("Invalid version {}!", version).into());
+                }
+                manifest.version = version.to_string();
+            }
+
+            if let Some(loader) = args.get(3) {
+                manifest.loader = loader.to_string();
+            }
+
+            manifest.save(&dir)?;
+
+            mdget::cinfoln(
+                format!(
+                    "Created {} for {} ({})",
+                    mdget::manifest::MANIFEST_FILE,
+                    style(&manifest.version).cyan(),
+                    style(&manifest.loader).cyan()
+                )
+                .as_str(),
+            );
+        }
         "mod" => {
-            let mods = std::env::args()
-                .skip(2)
-                .filter(|arg| !arg.starts_with("-"))
+            let mods = positional(&args[2..], &[])
+                .into_iter()
+                .map(|arg| arg.to_string())
                 .collect::<Vec<String>>();
 
-            // Set terminal raw mode
-
             mdget::cinfo("Collected mods: ");
 
             mods.iter()
@@ -183,256 +305,2369 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             println!();
 
-            if mods.len() == 0 {
-                mdget::cerror("Usage: mdget mod <modid> [modid...]");
+            if mods.is_empty() {
+                return Err("Usage: mdget mod <modid> [modid...]".into());
+            }
+
+            let dir = std::env::current_dir()?;
+
+            // Inside a project, record the mods in the manifest and let sync install them
+            if let Some(mut manifest) = Manifest::load(&dir)? {
+                for modid in &mods {
+                    manifest
+                        .mods
+                        .entry(modid.to_string())
+                        .or_insert_with(|| LATEST.to_string());
+                }
+
+                manifest.save(&dir)?;
+
+                sync(
+                    &sources,
+                    &manifest.config(&config),
+                    &manifest,
+                    &dir,
+                    &args,
+                    &mut state,
+                    None,
+                )?;
+
                 return Ok(());
             }
 
+            let mods = mods
+                .into_iter()
+                .map(|modid| (modid, LATEST.to_string()))
+                .collect::<BTreeMap<String, String>>();
+
             mdget::cinfoln("Preparing to query Modrinth API...");
 
-            let mut mods_data = Vec::new();
+            let resolution = Resolver::new(&sources, &config)
+                .on_optional(optional_prompt(&args))
+                .resolve(&mods);
+
+            check_conflicts(&resolution.conflicts)?;
+
+            let mut failed = resolution.failed;
+
+            let mut downloads = Vec::new();
+
+            for resolved in &resolution.mods {
+                match LockedMod::new(resolved) {
+                    Some(locked) => downloads.push(locked),
+                    None => failed.push((
+                        resolved.project.slug.clone(),
+                        mdget::Error::NoFiles {
+                            project: resolved.project.title.clone(),
+                        },
+                    )),
+                }
+            }
 
-            mods.iter().for_each(|modid| {
-                let mod_url = format!("{}/project/{}", mdget::API_URL, modid);
-                let dependencies_url = format!("{}/project/{}/dependencies", mdget::API_URL, modid);
-                let resp = client.get(mod_url.as_str()).headers(headers.clone()).send();
+            // save to current working directory
+            let files = downloads.iter().map(LockedMod::file).collect::<Vec<_>>();
+            let results = download_all(&client, &config, &files, &dir);
 
-                match resp {
-                    Ok(_) => {}
-                    Err(_) => {
-                        mdget::cerrorln(format!("Failed to query mod {}!", modid).as_str());
-                        return;
+            for (mut locked, result) in downloads.into_iter().zip(results) {
+                match result {
+                    Ok(hashes) => {
+                        locked.hashes = hashes;
+                        state.record(Install::new(&dir, &locked));
                     }
+                    Err(err) => failed.push((locked.slug.clone(), err)),
                 }
+            }
+
+            state.save()?;
 
-                let resp = resp.unwrap();
+            report_failures(&failed)?;
+        }
+        "info" => {
+            let project = match positional(&args[2..], &[]).first() {
+                Some(project) => project.to_string(),
+                None => {
+                    return Err("Usage: mdget info <project>".into());
+                }
+            };
 
-                if resp.status().is_success() {
-                    let data = resp.json::<ModrinthProject>().unwrap();
+            // Inside a project, show what would be installed for it
+            let dir = std::env::current_dir()?;
+            let target = match Manifest::load(&dir)? {
+                Some(manifest) => manifest.config(&config),
+                None => config.clone(),
+            };
 
-                    let exists = data.game_versions.iter().any(|v| v == &config.version);
+            info(&sources, &target, &project)?;
+        }
+        "list" => {
+            let dir = std::env::current_dir()?;
 
-                    if !exists {
-                        mdget::cerrorln(
-                            format!("Mod {} doesn't support version {}!", modid, config.version)
-                                .as_str(),
-                        );
-                        return;
-                    }
+            // The manifest's mods directory inside a project, otherwise the current one
+            let mods_dir = match Manifest::load(&dir)? {
+                Some(manifest) => manifest.mods_dir(&dir),
+                None => dir,
+            };
 
-                    mods_data.push(data);
+            let installs = if flag(&args, "--all") {
+                state.installs.iter().collect::<Vec<_>>()
+            } else {
+                state.installs_in(&mods_dir).collect::<Vec<_>>()
+            };
 
-                    let res = client
-                        .get(dependencies_url.as_str())
-                        .headers(headers.clone())
-                        .send();
+            if installs.is_empty() {
+                mdget::cinfoln("Nothing installed by mdget here");
+            }
 
-                    match res {
-                        Ok(_) => {}
-                        Err(_) => {
-                            mdget::cerrorln(format!("Failed to query mod {}!", modid).as_str());
-                            return;
+            for install in installs {
+                mdget::cinfoln(
+                    format!(
+                        "{} {} {}{}",
+                        style(&install.slug).cyan().bold(),
+                        install.version_number,
+                        style(install.path().display()).dim(),
+                        if install.requested {
+                            ""
+                        } else {
+                            " (dependency)"
                         }
-                    }
+                    )
+                    .as_str(),
+                );
+            }
+        }
+        "scan" => {
+            let dir = match positional(&args[2..], &[]).first() {
+                Some(dir) => PathBuf::from(dir),
+                None => std::env::current_dir()?,
+            };
 
-                    let res = res.unwrap();
+            scan(&client, &mut state, &dir)?;
+        }
+        "search" => search(&client, &config, &args)?,
+        "outdated" => {
+            let dir = std::env::current_dir()?;
 
-                    if res.status().is_success() {
-                        let data = res.json::<Dependencies>().unwrap();
+            let (mods_dir, target) = match Manifest::load(&dir)? {
+                Some(manifest) => (manifest.mods_dir(&dir), manifest.config(&config)),
+                None => (dir, config.clone()),
+            };
 
-                        if data.projects.is_empty() {
-                            mdget::cinfoln("No dependencies!");
-                            return;
-                        }
+            let outdated = outdated(&sources, &target, &state, &mods_dir);
 
-                        data.projects.iter().for_each(|dep| {
-                            let dep_url = format!("{}/project/{}", mdget::API_URL, dep.id);
-                            let resp = client.get(dep_url.as_str()).headers(headers.clone()).send();
-
-                            match resp {
-                                Ok(_) => {}
-                                Err(_) => {
-                                    mdget::cerrorln(
-                                        format!("Failed to query mod {}!", modid).as_str(),
-                                    );
-                                    return;
-                                }
-                            }
+            if outdated.is_empty() {
+                mdget::cinfoln("Everything is up to date!");
+            }
+
+            for (install, latest) in outdated {
+                mdget::cinfoln(
+                    format!(
+                        "{} {} -> {}",
+                        style(&install.slug).cyan().bold(),
+                        style(&install.version_number).red(),
+                        style(latest.version_number.as_deref().unwrap_or("?")).green()
+                    )
+                    .as_str(),
+                );
+            }
+        }
+        "update" => {
+            let dir = std::env::current_dir()?;
+            let only = positional(&args[2..], &[])
+                .into_iter()
+                .map(|modid| modid.to_string())
+                .collect::<Vec<_>>();
+
+            match Manifest::load(&dir)? {
+                Some(manifest) => sync(
+                    &sources,
+                    &manifest.config(&config),
+                    &manifest,
+                    &dir,
+                    &args,
+                    &mut state,
+                    Some(&only),
+                )?,
+                None => update(&sources, &config, &mut state, &dir, &only, &args)?,
+            }
+        }
+        "remove" => {
+            let targets = positional(&args[2..], &[]);
+
+            if targets.is_empty() {
+                return Err("Usage: mdget remove <modid> [modid...]".into());
+            }
+
+            let dir = std::env::current_dir()?;
 
-                            let resp = resp.unwrap();
+            match Manifest::load(&dir)? {
+                // Dropping mods from the manifest and syncing also drops their dependencies
+                Some(mut manifest) => {
+                    let lock = Lockfile::load(&dir)?.unwrap_or_default();
 
-                            if resp.status().is_success() {
-                                let data = resp.json::<ModrinthProject>().unwrap();
+                    for target in &targets {
+                        let locked = lock
+                            .mods
+                            .iter()
+                            .find(|m| m.slug == *target || m.project_id == *target);
 
-                                let exists =
-                                    data.game_versions.iter().any(|v| v == &config.version);
+                        let mut keys = vec![*target];
+
+                        if let Some(locked) = locked {
+                            keys.push(&locked.slug);
+                            keys.push(&locked.project_id);
+                        }
 
-                                if !exists {
-                                    mdget::cerrorln(
-                                        format!(
-                                            "Mod {} doesn't support version {}!",
-                                            modid, config.version
-                                        )
-                                        .as_str(),
-                                    );
-                                    return;
-                                }
+                        let key = keys
+                            .into_iter()
+                            .find(|key| manifest.mods.contains_key(*key));
 
-                                mods_data.push(data);
-                            } else {
-                                mdget::cerrorln(format!("Failed to query mod {}!", modid).as_str());
+                        match (key, locked) {
+                            (Some(key), _) => {
+                                manifest.mods.remove(key);
                             }
-                        });
+                            (None, Some(locked)) if !locked.requested => {
+                                return Err(format!(
+                                    "{} is only installed as a dependency of {}!",
+                                    target,
+                                    dependents(&lock, &locked.required_by)
+                                )
+                                .into());
+                            }
+                            _ => {
+                                return Err(format!(
+                                    "{} isn't in {}!",
+                                    target,
+                                    mdget::manifest::MANIFEST_FILE
+                                )
+                                .into())
+                            }
+                        }
                     }
-                } else {
-                    mdget::cerrorln(format!("Failed to query mod {}!", modid).as_str());
+
+                    manifest.save(&dir)?;
+
+                    sync(
+                        &sources,
+                        &manifest.config(&config),
+                        &manifest,
+                        &dir,
+                        &args,
+                        &mut state,
+                        None,
+                    )?;
                 }
-            });
+                None => remove(&mut state, &dir, &targets, flag(&args, "--force"))?,
+            }
+        }
+        "sync" => {
+            let dir = std::env::current_dir()?;
+
+            let manifest = match Manifest::load(&dir)? {
+                Some(manifest) => manifest,
+                None => {
+                    return Err(format!(
+                        "No {} found! Create one with mdget init",
+                        mdget::manifest::MANIFEST_FILE
+                    )
+                    .into());
+                }
+            };
 
-            mods_data.iter().for_each(|data| {
-                mdget::cinfoln(format!("Downloading mod {}...", data.title).as_str());
+            sync(
+                &sources,
+                &manifest.config(&config),
+                &manifest,
+                &dir,
+                &args,
+                &mut state,
+                None,
+            )?;
+        }
+        "cache" => cache(&config, &state, &data_dir, &args)?,
+        "packwiz" => match args.get(2).map(|arg| arg.as_str()) {
+            Some("install") => packwiz_install(&sources, &config, &mut state, &args)?,
+            Some("export") => packwiz_export(&sources, &config, &mut state, &args)?,
+            _ => {
+                mdget::cerrorln("Usage: mdget packwiz install <pack.toml> [dir]");
+                mdget::cerrorln("       mdget packwiz export");
+                return Err("Missing or invalid packwiz command".into());
+            }
+        },
+        "pack" => match args.get(2).map(|arg| arg.as_str()) {
+            Some("import") => pack_import(&client, &config, &mut state, &args)?,
+            Some("export") => pack_export(&sources, &config, &state, &args)?,
+            _ => {
+                mdget::cerrorln("Usage: mdget pack import <file.mrpack> <dir>");
+                mdget::cerrorln("       mdget pack export <file.mrpack>");
+                return Err("Missing or invalid pack command".into());
+            }
+        },
+        _ => {
+            return Err(format!("Invalid command {}!", style(&args[1]).red()).into());
+        }
+    }
 
-                let version_url = format!("{}/project/{}/version", mdget::API_URL, data.id);
+    // Save the config file
+    let toml = toml::to_string(&config)?;
 
-                let resp = client
-                    .get(version_url.as_str())
-                    .headers(headers.clone())
-                    .send();
+    // write the config to the config file
+    std::fs::write(&config_path, toml)?;
 
-                match resp {
-                    Ok(_) => {}
-                    Err(_) => {
-                        mdget::cerrorln(format!("Failed to query mod {}!", data.title).as_str());
-                        return;
-                    }
-                }
+    Ok(())
+}
 
-                let resp = resp.unwrap();
+// Number of compatible versions listed by mdget info
+const INFO_VERSIONS: usize = 10;
+
+fn info(
+    sources: &Sources,
+    config: &Config,
+    project: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = sources.project(project)?;
+
+    mdget::cinfoln(
+        format!(
+            "{} ({}) {}",
+            style(&data.title).cyan().bold(),
+            data.slug,
+            style(&data.project_type).dim()
+        )
+        .as_str(),
+    );
+    mdget::cinfoln(format!("  {}", data.description).as_str());
+    mdget::cinfoln(
+        format!(
+            "License: {}  Downloads: {}  Followers: {}",
+            style(&data.license.name).green(),
+            style(data.downloads).green(),
+            style(data.followers).green()
+        )
+        .as_str(),
+    );
+    mdget::cinfoln(format!("Categories: {}", data.categories.join(", ")).as_str());
+    mdget::cinfoln(
+        format!(
+            "Client: {}  Server: {}",
+            style(&data.client_side).green(),
+            style(&data.server_side).green()
+        )
+        .as_str(),
+    );
 
-                let version_data = resp.json::<ProjectVersions>();
+    let links = [
+        ("Source", data.source_url.as_deref()),
+        ("Issues", data.issues_url.as_deref()),
+        ("Wiki", data.wiki_url.as_str()),
+        ("Discord", data.discord_url.as_deref()),
+    ];
 
-                match version_data {
-                    Ok(_) => {}
-                    Err(err) => {
-                        mdget::cerrorln(format!("Failed to query mod {}!", data.title).as_str());
-                        mdget::cerrorln(format!("{}", err).as_str());
-                        return;
-                    }
-                }
+    for (name, url) in links {
+        if let Some(url) = url {
+            mdget::cinfoln(format!("{}: {}", name, style(url).cyan()).as_str());
+        }
+    }
 
-                let version_data = version_data.unwrap();
+    for donation in &data.donation_urls {
+        mdget::cinfoln(
+            format!(
+                "Donate ({}): {}",
+                donation.platform,
+                style(&donation.url).cyan()
+            )
+            .as_str(),
+        );
+    }
 
-                let versions: Vec<&ProjectVersion> = version_data
-                    .iter()
-                    .filter(|v| {
-                        v.game_versions.iter().any(|v| v == &config.version)
-                            && v.loaders.iter().any(|l| l == &config.loader)
-                    })
-                    .collect();
-
-                if versions.is_empty() {
-                    mdget::cerrorln(
-                        format!(
-                            "Mod {} doesn't support version {} or loader {}!",
-                            style(&data.title).cyan(),
-                            style(&config.version).cyan(),
-                            style(&config.loader).cyan()
-                        )
-                        .as_str(),
-                    );
-                    return;
-                }
+    let mut versions = sources.supported_versions(&data.id, &config.version, &config.loader)?;
 
-                // Get latest file
-                let file = versions.iter().max_by_key(|v| &v.date_published);
-
-                if file.is_none() {
-                    mdget::cerrorln(
-                        format!(
-                            "Mod {} doesn't support version {}!",
-                            data.title, config.version
-                        )
-                        .as_str(),
-                    );
-                    return;
-                }
+    versions.sort_by(|a, b| b.date_published.cmp(&a.date_published));
+
+    if versions.is_empty() {
+        mdget::cwarnln(
+            format!(
+                "No versions for {} ({})",
+                style(&config.version).cyan(),
+                style(&config.loader).cyan()
+            )
+            .as_str(),
+        );
+        return Ok(());
+    }
+
+    mdget::cinfoln(
+        format!(
+            "Versions for {} ({}):",
+            style(&config.version).cyan(),
+            style(&config.loader).cyan()
+        )
+        .as_str(),
+    );
+
+    for version in versions.iter().take(INFO_VERSIONS) {
+        mdget::cinfoln(
+            format!(
+                "  {} {} {}",
+                style(version.version_number.as_deref().unwrap_or("?")).cyan(),
+                version.version_type.as_deref().unwrap_or(""),
+                style(version.date_published.as_deref().unwrap_or("")).dim()
+            )
+            .as_str(),
+        );
+    }
 
-                let file = file.unwrap();
+    if versions.len() > INFO_VERSIONS {
+        mdget::cinfoln(format!("  ... and {} more", versions.len() - INFO_VERSIONS).as_str());
+    }
 
-                let version_number = file.version_number.clone().unwrap();
+    let latest = &versions[0];
 
-                let file_url = &file.files[0].url;
+    if let Some(changelog) = latest.changelog.as_deref().filter(|c| !c.trim().is_empty()) {
+        mdget::cinfoln(
+            format!(
+                "Changelog for {}:",
+                style(latest.version_number.as_deref().unwrap_or("?")).cyan()
+            )
+            .as_str(),
+        );
 
-                mdget::cinfoln(&format!(
-                    "Downloading file: {}",
-                    style(&file.files[0].filename).cyan()
-                ));
+        for line in changelog.lines() {
+            println!("    {}", line);
+        }
+    }
 
-                let mut hasher = Sha512::new();
+    mdget::cinfoln("Preparing to query Modrinth API...");
 
-                let resp = client.get(file_url).headers(headers.clone()).send();
+    let mods = BTreeMap::from([(data.id.clone(), LATEST.to_string())]);
+    let resolution = Resolver::new(sources, config).resolve(&mods);
 
-                match resp {
-                    Ok(_) => {}
-                    Err(_) => {
-                        mdget::cerrorln(format!("Failed to download file {}!", file_url).as_str());
-                        return;
-                    }
-                }
+    mdget::cinfoln("Dependencies:");
+
+    match resolution.mods.first() {
+        Some(root) if resolution.mods.len() > 1 => {
+            print_dependency_tree(&resolution, &root.project.id, 1, &mut HashSet::new())
+        }
+        _ => mdget::cinfoln("  None"),
+    }
+
+    for (project, err) in &resolution.failed {
+        mdget::cwarnln(format!("  {}: {}", style(project).red(), err).as_str());
+    }
+
+    for conflict in &resolution.conflicts {
+        for line in conflict.to_string().lines() {
+            mdget::cwarnln(line);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_dependency_tree(
+    resolution: &Resolution,
+    id: &str,
+    depth: usize,
+    seen: &mut HashSet<String>,
+) {
+    for dependency in resolution
+        .mods
+        .iter()
+        .filter(|r| r.required_by.iter().any(|parent| parent == id))
+    {
+        mdget::cinfoln(
+            format!(
+                "{}{} {}",
+                "  ".repeat(depth),
+                style(&dependency.project.title).cyan(),
+                dependency.version.version_number.as_deref().unwrap_or("?")
+            )
+            .as_str(),
+        );
+
+        if seen.insert(dependency.project.id.clone()) {
+            print_dependency_tree(resolution, &dependency.project.id, depth + 1, seen);
+        }
+    }
+}
+
+// Options of mdget search that take a value
+const SEARCH_OPTIONS: &[&str] = &["--type", "--category", "--side", "--sort", "--limit"];
+
+// mdget search <query> [--type <type>] [--category <category>]... [--side client|server]
+//   [--sort relevance|downloads|follows|newest|updated] [--limit <n>] [--any-version] [--json]
+fn search(
+    client: &ModrinthClient,
+    config: &Config,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let query = positional(&args[2..], SEARCH_OPTIONS).join(" ");
+    let project_type = option(args, "--type").unwrap_or("mod");
+
+    let mut facets = vec![vec![format!("project_type:{}", project_type)]];
+
+    if !flag(args, "--any-version") {
+        facets.push(vec![format!("versions:{}", config.version)]);
+
+        // Loaders are only categories of mods and modpacks
+        if project_type == "mod" || project_type == "modpack" {
+            facets.push(vec![format!("categories:{}", config.loader)]);
+        }
+    }
+
+    for category in options(args, "--category") {
+        facets.push(vec![format!("categories:{}", category)]);
+    }
+
+    match option(args, "--side") {
+        Some(side @ ("client" | "server")) => facets.push(vec![
+            format!("{}_side:required", side),
+            format!("{}_side:optional", side),
+        ]),
+        Some(side) => return Err(format!("Invalid side {}!", side).into()),
+        None => {}
+    }
+
+    let index = match option(args, "--sort") {
+        Some(index @ ("relevance" | "downloads" | "follows" | "newest" | "updated")) => {
+            Some(index.to_string())
+        }
+        Some(index) => return Err(format!("Invalid sort order {}!", index).into()),
+        None => None,
+    };
+
+    let limit = option(args, "--limit")
+        .map(|limit| limit.parse::<u32>())
+        .transpose()?;
+
+    let results = client.search(&SearchQuery {
+        query,
+        facets,
+        index,
+        limit,
+        offset: None,
+    })?;
+
+    if flag(args, "--json") {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    if results.hits.is_empty() {
+        mdget::cwarnln("No results!");
+        return Ok(());
+    }
+
+    mdget::cinfoln(
+        format!(
+            "Showing {} of {} results",
+            results.hits.len(),
+            results.total_hits
+        )
+        .as_str(),
+    );
+
+    for hit in &results.hits {
+        mdget::cinfoln(
+            format!(
+                "{} {} by {}",
+                style(&hit.slug).cyan().bold(),
+                hit.title,
+                style(&hit.author).green()
+            )
+            .as_str(),
+        );
+        mdget::cinfoln(
+            format!(
+                "  {} {}",
+                hit.description,
+                style(format!(
+                    "({} downloads, {} follows)",
+                    hit.downloads, hit.follows
+                ))
+                .dim()
+            )
+            .as_str(),
+        );
+    }
+
+    Ok(())
+}
+
+// Installed mods with a newer version for the game version and loader
+fn outdated(
+    sources: &Sources,
+    config: &Config,
+    state: &State,
+    mods_dir: &Path,
+) -> Vec<(Install, ProjectVersion)> {
+    let mut outdated = Vec::new();
+
+    for install in state.installs_in(mods_dir) {
+        let versions = match sources.supported_versions(
+            &install.project_id,
+            &config.version,
+            &config.loader,
+        ) {
+            Ok(versions) => versions,
+            Err(err) => {
+                mdget::cerrorln(format!("{}: {}", style(&install.slug).red(), err).as_str());
+                continue;
+            }
+        };
+
+        let latest = versions
+            .into_iter()
+            .max_by(|a, b| a.date_published.cmp(&b.date_published));
+
+        if let Some(latest) = latest {
+            if latest.id.as_deref() != Some(install.version_id.as_str()) {
+                outdated.push((install.clone(), latest));
+            }
+        }
+    }
+
+    outdated
+}
+
+// Update mods installed outside of a project to their latest versions, replacing the old
+// jars. Updates everything that was requested if only is empty.
+fn update(
+    sources: &Sources,
+    config: &Config,
+    state: &mut State,
+    dir: &Path,
+    only: &[String],
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let installs = state.installs_in(dir).cloned().collect::<Vec<_>>();
+
+    let mods = installs
+        .iter()
+        .filter(|i| match only {
+            [] => i.requested,
+            only => only.contains(&i.slug) || only.contains(&i.project_id),
+        })
+        .map(|i| (i.project_id.clone(), LATEST.to_string()))
+        .collect::<BTreeMap<String, String>>();
+
+    if mods.is_empty() {
+        mdget::cwarnln("Nothing to update!");
+        return Ok(());
+    }
+
+    mdget::cinfoln("Preparing to query Modrinth API...");
+
+    let resolution = Resolver::new(sources, config)
+        .on_optional(optional_prompt(args))
+        .resolve(&mods);
+
+    check_conflicts(&resolution.conflicts)?;
 
-                let mut resp = resp.unwrap();
+    let mut failed = resolution.failed;
+    let mut downloads = Vec::new();
 
-                let file_data = resp.bytes();
+    for resolved in &resolution.mods {
+        let old = installs
+            .iter()
+            .find(|i| i.project_id == resolved.project.id);
 
-                match file_data {
-                    Ok(_) => {}
-                    Err(_) => {
-                        mdget::cerrorln(format!("Failed to download file {}!", file_url).as_str());
-                        return;
+        if old.is_some_and(|old| resolved.version.id.as_ref() == Some(&old.version_id)) {
+            continue;
+        }
+
+        match LockedMod::new(resolved) {
+            Some(locked) => downloads.push((locked, old)),
+            None => failed.push((
+                resolved.project.slug.clone(),
+                mdget::Error::NoFiles {
+                    project: resolved.project.title.clone(),
+                },
+            )),
+        }
+    }
+
+    let files = downloads
+        .iter()
+        .map(|(locked, _)| locked.file())
+        .collect::<Vec<_>>();
+    let results = download_all(sources.client(), config, &files, dir);
+
+    for ((mut locked, old), result) in downloads.into_iter().zip(results) {
+        match result {
+            Ok(hashes) => locked.hashes = hashes,
+            Err(err) => {
+                failed.push((locked.slug.clone(), err));
+                continue;
+            }
+        }
+
+        let install = Install::new(dir, &locked);
+
+        if let Some(old) = old {
+            if old.filename != locked.filename {
+                if let Err(err) = std::fs::remove_file(old.path()) {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        failed.push((locked.slug.clone(), err.into()));
                     }
                 }
+            }
 
-                let file_data = file_data.unwrap();
+            mdget::cinfoln(
+                format!(
+                    "Updated {} {} -> {}",
+                    style(&locked.slug).cyan(),
+                    style(&old.version_number).red(),
+                    style(&locked.version_number).green()
+                )
+                .as_str(),
+            );
+        }
 
-                hasher.update(&file_data);
+        state.record(install);
+    }
 
-                let hash = hasher.finalize();
+    state.save()?;
 
-                let hash = format!("{:x}", hash);
+    report_failures(&failed)
+}
 
-                mdget::cinfoln(
-                    format!(
-                        "Verifying file {} with hash {}...",
-                        style(file_url).cyan(),
-                        style(&hash[0..15]).green()
-                    )
-                    .as_str(),
-                );
+// Identify the jars in dir by hash and start tracking the ones Modrinth knows about
+fn scan(
+    client: &ModrinthClient,
+    state: &mut State,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !dir.is_dir() {
+        return Err(format!("{} isn't a directory!", dir.display()).into());
+    }
 
-                if hash != file.files[0].hashes.sha512 {
-                    mdget::cerrorln(format!("Failed to verify file {}!", file_url).as_str());
-                    return;
-                }
+    let mut files = Vec::new();
 
-                // save to current working directory
-                let mut path = std::env::current_dir().unwrap();
-                path.push(&file.files[0].filename);
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
 
-                let mut file = std::fs::File::create(&path).unwrap();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "jar") {
+            continue;
+        }
 
-                file.write_all(&file_data).unwrap();
-            });
+        let filename = path.file_name().unwrap().to_string_lossy().to_string();
+        let hashes = mdget::hash_file(&path)?;
+
+        let tracked = state
+            .installs_in(dir)
+            .any(|i| i.filename == filename && i.sha512 == hashes.sha512);
+
+        if !tracked {
+            files.push((filename, hashes));
         }
-        _ => {
-            mdget::cerrorln(format!("Invalid command {}!", style(&args[1]).red()).as_str());
+    }
+
+    if files.is_empty() {
+        mdget::cinfoln("No untracked jars found!");
+        return Ok(());
+    }
+
+    mdget::cinfoln(format!("Looking up {} jars...", files.len()).as_str());
+
+    let sha512s = files
+        .iter()
+        .map(|(_, hashes)| hashes.sha512.clone())
+        .collect::<Vec<_>>();
+
+    let mut found = client.version_files(&sha512s, "sha512")?;
+
+    // Fall back to sha1 for anything sha512 didn't find
+    let sha1s = files
+        .iter()
+        .filter(|(_, hashes)| !found.contains_key(&hashes.sha512))
+        .map(|(_, hashes)| hashes.sha1.clone())
+        .collect::<Vec<_>>();
+
+    if !sha1s.is_empty() {
+        for (sha1, version) in client.version_files(&sha1s, "sha1")? {
+            if let Some((_, hashes)) = files.iter().find(|(_, hashes)| hashes.sha1 == sha1) {
+                found.insert(hashes.sha512.clone(), version);
+            }
         }
     }
 
-    // Save the config file
-    let toml = toml::to_string(&config)?;
+    let mut project_ids = found
+        .values()
+        .filter_map(|version| version.project_id.clone())
+        .collect::<Vec<_>>();
+    project_ids.sort();
+    project_ids.dedup();
 
-    // write the config to the config file
-    std::fs::write(&config_path, toml)?;
+    let projects = if project_ids.is_empty() {
+        Vec::new()
+    } else {
+        client.projects(&project_ids)?
+    };
+
+    let mut adopted = Vec::new();
+    let mut unknown = Vec::new();
+
+    for (filename, hashes) in files {
+        let version = found.get(&hashes.sha512);
+        let project = version.and_then(|version| {
+            projects
+                .iter()
+                .find(|p| version.project_id.as_ref() == Some(&p.id))
+        });
+
+        let (version, project) = match (version, project) {
+            (Some(version), Some(project)) => (version, project),
+            _ => {
+                unknown.push(filename);
+                continue;
+            }
+        };
+
+        let file = version
+            .files
+            .iter()
+            .find(|f| f.hashes.sha512 == hashes.sha512)
+            .cloned()
+            .unwrap_or_else(|| File {
+                hashes,
+                ..Default::default()
+            });
+
+        let mut locked = LockedMod::for_file(project, version, &file);
+
+        // The jar may have been renamed by hand
+        locked.filename = filename;
+
+        adopted.push((locked, version));
+    }
+
+    // Jars required by other adopted jars count as dependencies
+    let edges = adopted
+        .iter()
+        .flat_map(|(locked, version)| {
+            version
+                .dependencies
+                .iter()
+                .filter(|d| d.dependency_type.as_deref() == Some("required"))
+                .filter_map(|d| d.project_id.clone())
+                .map(|dependency| (dependency, locked.project_id.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for (dependency, parent) in edges {
+        if let Some((locked, _)) = adopted
+            .iter_mut()
+            .find(|(locked, _)| locked.project_id == dependency)
+        {
+            locked.requested = false;
+            locked.required_by.push(parent);
+        }
+    }
+
+    for (locked, _) in &adopted {
+        mdget::cinfoln(
+            format!(
+                "Adopted {} {} ({})",
+                style(&locked.slug).cyan().bold(),
+                locked.version_number,
+                style(&locked.filename).dim()
+            )
+            .as_str(),
+        );
+
+        state.record(Install::new(dir, locked));
+    }
+
+    if !unknown.is_empty() {
+        mdget::cwarnln(format!("{} jars aren't on Modrinth:", unknown.len()).as_str());
+
+        for filename in &unknown {
+            mdget::cwarnln(format!("  {}", style(filename).yellow()).as_str());
+        }
+    }
+
+    state.save()?;
 
     Ok(())
 }
+
+// mdget cache list/prune/clear
+fn cache(
+    config: &Config,
+    state: &State,
+    data_dir: &Path,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = Cache::open(data_dir, config.cache_link)?;
+    let limit = config.cache_size * 1024 * 1024;
+
+    match args.get(2).map(|arg| arg.as_str()) {
+        Some("list") | None => {
+            let mut entries = cache.entries()?;
+            entries.sort_by_key(|e| std::cmp::Reverse(e.used));
+
+            for entry in &entries {
+                let mut names = state
+                    .installs
+                    .iter()
+                    .filter(|i| i.sha512 == entry.sha512)
+                    .map(|i| i.filename.as_str())
+                    .collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+
+                let names = if names.is_empty() {
+                    style("(not installed)".to_string()).yellow()
+                } else {
+                    style(names.join(", ")).cyan()
+                };
+
+                mdget::cinfoln(
+                    format!(
+                        "{} {} {}",
+                        names,
+                        HumanBytes(entry.size),
+                        style(&entry.sha512[..entry.sha512.len().min(12)]).dim()
+                    )
+                    .as_str(),
+                );
+            }
+
+            mdget::cinfoln(
+                format!(
+                    "{} files, {} of {} in {}",
+                    entries.len(),
+                    HumanBytes(entries.iter().map(|e| e.size).sum()),
+                    HumanBytes(limit),
+                    style(cache.dir().display()).dim()
+                )
+                .as_str(),
+            );
+        }
+        Some("prune") => {
+            let mut freed = 0;
+
+            // Drop whatever no directory uses anymore, then anything over the size limit
+            for entry in cache.entries()? {
+                if !state.installs.iter().any(|i| i.sha512 == entry.sha512) {
+                    cache.remove(&entry.sha512)?;
+                    freed += entry.size;
+                }
+            }
+
+            freed += cache.evict(limit)?.iter().map(|e| e.size).sum::<u64>();
+
+            mdget::cinfoln(format!("Freed {}", HumanBytes(freed)).as_str());
+        }
+        Some("clear") => {
+            let size = cache.size()?;
+
+            cache.clear()?;
+
+            mdget::cinfoln(format!("Freed {}", HumanBytes(size)).as_str());
+        }
+        Some(other) => {
+            mdget::cerrorln("Usage: mdget cache [list|prune|clear]");
+            return Err(format!("Invalid cache command {}!", style(other).red()).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn minecraft_versions(client: &ModrinthClient) -> mdget::Result<MinecraftVersions> {
+    mdget::cinfoln(" Getting Minecraft versions from API...");
+
+    let versions = client.minecraft_versions()?;
+
+    mdget::cinfoln(" Done!");
+
+    Ok(versions)
+}
+
+// The side a pack is installed for, --side client (the default) or server
+fn parse_side(args: &[String]) -> Result<&str, Box<dyn std::error::Error>> {
+    let side = option(args, "--side").unwrap_or("client");
+
+    if side != "client" && side != "server" {
+        return Err(format!("Invalid side {}, expected client or server", side).into());
+    }
+
+    Ok(side)
+}
+
+// Track the mods a pack put into dir like mdget scan would, so they can be updated and removed
+fn adopt_mods(client: &ModrinthClient, state: &mut State, dir: &Path) {
+    let mods_dir = dir.join("mods");
+
+    if mods_dir.is_dir() && !client.is_offline() {
+        if let Err(err) = scan(client, state, &mods_dir) {
+            mdget::cwarnln(format!("Couldn't look up the pack's mods: {}", err).as_str());
+        }
+    }
+}
+
+// mdget pack import <file.mrpack> <dir> [--side client|server] [--no-optional]
+fn pack_import(
+    client: &ModrinthClient,
+    config: &Config,
+    state: &mut State,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (file, dir) = match positional(&args[3..], &["--side"])[..] {
+        [file, dir, ..] => (PathBuf::from(file), PathBuf::from(dir)),
+        _ => {
+            return Err("Usage: mdget pack import <file.mrpack> <dir>".into());
+        }
+    };
+
+    let side = parse_side(args)?;
+
+    let mut pack = Pack::open(&file)?;
+    let index = pack.index.clone();
+
+    mdget::cinfoln(
+        format!(
+            "Importing {} {} ({})",
+            style(&index.name).cyan().bold(),
+            index.version_id,
+            index
+                .dependencies
+                .iter()
+                .map(|(name, version)| format!("{} {}", name, version))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .as_str(),
+    );
+
+    let mut failed: Failures = Vec::new();
+
+    // Download into each directory the files are in
+    let mut groups = BTreeMap::<PathBuf, Vec<&PackFile>>::new();
+
+    for pack_file in &index.files {
+        let path = pack_file.check(&index.name)?;
+
+        match pack_file.support(side) {
+            "unsupported" => continue,
+            "optional" if flag(args, "--no-optional") => {
+                mdget::cinfoln(
+                    format!("Skipping optional {}", style(&pack_file.path).cyan()).as_str(),
+                );
+                continue;
+            }
+            _ => {}
+        }
+
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        groups.entry(parent).or_default().push(pack_file);
+    }
+
+    for (parent, pack_files) in groups {
+        let target = dir.join(parent);
+
+        std::fs::create_dir_all(&target)?;
+
+        let files = pack_files.iter().map(|f| f.file()).collect::<Vec<_>>();
+        let results = download_all(client, config, &files, &target);
+
+        for (pack_file, result) in pack_files.iter().zip(results) {
+            // Checked against both the sha512 and sha1 of the pack
+            if let Err(err) = result {
+                failed.push((pack_file.path.clone(), err));
+            }
+        }
+    }
+
+    let extracted = pack.extract_overrides(&dir, side)?;
+
+    mdget::cinfoln(format!("Extracted {} override files", extracted).as_str());
+
+    adopt_mods(client, state, &dir);
+
+    report_failures(&failed)?;
+
+    mdget::cinfoln(
+        format!(
+            "Imported {} into {}",
+            style(&index.name).cyan(),
+            style(dir.display()).cyan()
+        )
+        .as_str(),
+    );
+
+    Ok(())
+}
+
+// A file of a packwiz pack at location, a path or URL of pack.toml or index.toml, or of the
+// directory it is in
+fn packwiz_location(location: &str, file: &str) -> String {
+    match reqwest::Url::parse(location) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url
+            .join(file)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| file.to_string()),
+        _ => {
+            let path = Path::new(location);
+            let dir = if path.is_dir() {
+                path
+            } else {
+                path.parent().unwrap_or(Path::new("."))
+            };
+
+            dir.join(file).to_string_lossy().to_string()
+        }
+    }
+}
+
+fn read_packwiz_file(client: &ModrinthClient, location: &str) -> mdget::Result<Vec<u8>> {
+    if !(location.starts_with("http://") || location.starts_with("https://")) {
+        return Ok(std::fs::read(location)?);
+    }
+
+    let resp = client
+        .http()
+        .get(location)
+        .send()
+        .map_err(mdget::Error::Network)?;
+
+    if !resp.status().is_success() {
+        return Err(mdget::Error::Status {
+            url: location.to_string(),
+            status: resp.status(),
+        });
+    }
+
+    Ok(resp.bytes().map_err(mdget::Error::Network)?.to_vec())
+}
+
+// Check data against a packwiz hash, skipping formats mdget can't compute
+fn check_packwiz_hash(file: &str, format: &str, expected: &str, data: &[u8]) -> mdget::Result<()> {
+    match mdget::packwiz::hash(format, data) {
+        Some(actual) if !actual.eq_ignore_ascii_case(expected) => Err(mdget::Error::HashMismatch {
+            file: file.to_string(),
+            expected: expected.to_string(),
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn parse_packwiz<T: serde::de::DeserializeOwned>(location: &str, data: &[u8]) -> mdget::Result<T> {
+    toml::from_str(&String::from_utf8_lossy(data)).map_err(|source| mdget::Error::Parse {
+        path: PathBuf::from(location),
+        source,
+    })
+}
+
+// The file a metafile downloads. Downloads are checked against sha512 or sha1, so the source
+// the file is from is asked for those if the metafile has another hash or no URL, as
+// CurseForge metafiles don't.
+fn packwiz_download(sources: &Sources, meta: &ModToml) -> mdget::Result<File> {
+    let mut file = File {
+        url: meta.download.url.clone(),
+        filename: meta.filename.clone(),
+        primary: true,
+        ..Default::default()
+    };
+
+    match meta.download.hash_format.as_str() {
+        "sha512" => file.hashes.sha512 = meta.download.hash.clone(),
+        "sha1" => file.hashes.sha1 = meta.download.hash.clone(),
+        _ => {}
+    }
+
+    let hashed = !file.hashes.sha512.is_empty() || !file.hashes.sha1.is_empty();
+
+    if !file.url.is_empty() && hashed {
+        return Ok(file);
+    }
+
+    let version = match &meta.update {
+        Some(ModUpdate {
+            modrinth: Some(modrinth),
+            ..
+        }) => sources.version(&modrinth.mod_id, &modrinth.version)?,
+        Some(ModUpdate {
+            curseforge: Some(curseforge),
+            ..
+        }) => sources.version(
+            &mdget::curseforge::project_id(curseforge.project_id),
+            &curseforge.file_id.to_string(),
+        )?,
+        _ if file.url.is_empty() => {
+            return Err(mdget::Error::NoFiles {
+                project: meta.name.clone(),
+            })
+        }
+        _ => {
+            return Err(mdget::Error::UnsupportedHash {
+                file: meta.filename.clone(),
+                format: meta.download.hash_format.clone(),
+            })
+        }
+    };
+
+    let found = version
+        .files
+        .into_iter()
+        .find(|f| f.filename == meta.filename)
+        .ok_or_else(|| mdget::Error::NoFiles {
+            project: meta.name.clone(),
+        })?;
+
+    // Keep the metafile's URL, it may point somewhere on purpose
+    if file.url.is_empty() {
+        file.url = found.url;
+    }
+
+    file.hashes = found.hashes;
+    file.size = found.size;
+
+    Ok(file)
+}
+
+// mdget packwiz install <pack.toml> [dir] [--side client|server] [--no-optional]
+// pack.toml can be a path, the directory it is in or a URL
+fn packwiz_install(
+    sources: &Sources,
+    config: &Config,
+    state: &mut State,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = sources.client();
+
+    let (source, dir) = match positional(&args[3..], &["--side"])[..] {
+        [source, dir, ..] => (source, PathBuf::from(dir)),
+        [source] => (source, std::env::current_dir()?),
+        _ => {
+            return Err("Usage: mdget packwiz install <pack.toml> [dir]".into());
+        }
+    };
+
+    let side = parse_side(args)?;
+
+    let pack_location = if Path::new(source).is_dir() {
+        packwiz_location(source, PACK_FILE)
+    } else {
+        source.to_string()
+    };
+
+    let pack: PackToml =
+        parse_packwiz(&pack_location, &read_packwiz_file(client, &pack_location)?)?;
+
+    mdget::cinfoln(
+        format!(
+            "Installing {}{} ({})",
+            style(&pack.name).cyan().bold(),
+            pack.version
+                .as_ref()
+                .map(|version| format!(" {}", version))
+                .unwrap_or_default(),
+            pack.versions
+                .iter()
+                .map(|(name, version)| format!("{} {}", name, version))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .as_str(),
+    );
+
+    if !mdget::pack::inside(Path::new(&pack.index.file)) {
+        return Err(mdget::Error::UnsafePath {
+            pack: pack.name.clone(),
+            path: pack.index.file.clone(),
+        }
+        .into());
+    }
+
+    let index_location = packwiz_location(&pack_location, &pack.index.file);
+    let index_data = read_packwiz_file(client, &index_location)?;
+
+    check_packwiz_hash(
+        &pack.index.file,
+        &pack.index.hash_format,
+        &pack.index.hash,
+        &index_data,
+    )?;
+
+    let index: IndexToml = parse_packwiz(&index_location, &index_data)?;
+
+    let mut failed: Failures = Vec::new();
+    let mut groups = BTreeMap::<PathBuf, Vec<File>>::new();
+    let mut copied = 0;
+
+    for entry in &index.files {
+        let relative = PathBuf::from(&entry.file);
+
+        if !mdget::pack::inside(&relative) {
+            return Err(mdget::Error::UnsafePath {
+                pack: pack.name.clone(),
+                path: entry.file.clone(),
+            }
+            .into());
+        }
+
+        let location = packwiz_location(&index_location, &entry.file);
+        let format = entry.hash_format.as_deref().unwrap_or(&index.hash_format);
+
+        let data = match read_packwiz_file(client, &location).and_then(|data| {
+            check_packwiz_hash(&entry.file, format, &entry.hash, &data).map(|_| data)
+        }) {
+            Ok(data) => data,
+            Err(err) => {
+                failed.push((entry.file.clone(), err));
+                continue;
+            }
+        };
+
+        if !entry.metafile {
+            let target = dir.join(&relative);
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::write(target, data)?;
+            copied += 1;
+            continue;
+        }
+
+        let meta: ModToml = match parse_packwiz(&location, &data) {
+            Ok(meta) => meta,
+            Err(err) => {
+                failed.push((entry.file.clone(), err));
+                continue;
+            }
+        };
+
+        // The jar goes next to its metafile, a filename with a path in it could put it anywhere
+        let mut components = Path::new(&meta.filename).components();
+
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(mdget::Error::UnsafePath {
+                pack: pack.name.clone(),
+                path: meta.filename.clone(),
+            }
+            .into());
+        }
+
+        if !meta.supports(side) {
+            continue;
+        }
+
+        if meta.optional() && flag(args, "--no-optional") {
+            mdget::cinfoln(format!("Skipping optional {}", style(&meta.name).cyan()).as_str());
+            continue;
+        }
+
+        let file = match packwiz_download(sources, &meta) {
+            Ok(file) => file,
+            Err(err) => {
+                failed.push((entry.file.clone(), err));
+                continue;
+            }
+        };
+
+        let parent = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        groups.entry(parent).or_default().push(file);
+    }
+
+    for (parent, files) in groups {
+        let target = dir.join(parent);
+
+        std::fs::create_dir_all(&target)?;
+
+        let results = download_all(client, config, &files, &target);
+
+        for (file, result) in files.iter().zip(results) {
+            if let Err(err) = result {
+                failed.push((file.filename.clone(), err));
+            }
+        }
+    }
+
+    mdget::cinfoln(format!("Copied {} files from the pack", copied).as_str());
+
+    adopt_mods(client, state, &dir);
+
+    report_failures(&failed)?;
+
+    mdget::cinfoln(
+        format!(
+            "Installed {} into {}",
+            style(&pack.name).cyan(),
+            style(dir.display()).cyan()
+        )
+        .as_str(),
+    );
+
+    Ok(())
+}
+
+// Name of the packwiz metafile for an install without the extension: its slug, prefixed with
+// the source for mods that aren't from Modrinth, e.g. curseforge-jei next to jei
+fn metafile_name(install: &Install) -> String {
+    match install.project_id.split_once(':') {
+        Some((source, _)) => format!("{}-{}", source, install.slug),
+        None => install.slug.clone(),
+    }
+}
+
+const PACKWIZ_EXPORT_OPTIONS: &[&str] = &[
+    "--name",
+    "--author",
+    "--version",
+    "--loader-version",
+    "--override",
+];
+
+// mdget packwiz export [--name <name>] [--author <author>] [--version <version>]
+//     [--loader-version <version>] [--override <dir>...]
+// Writes pack.toml, index.toml and a .pw.toml for every mod mdget tracks in the current
+// directory. Entries of an existing index that mdget doesn't manage are kept.
+fn packwiz_export(
+    sources: &Sources,
+    config: &Config,
+    state: &mut State,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !positional(&args[3..], PACKWIZ_EXPORT_OPTIONS).is_empty() {
+        return Err("Usage: mdget packwiz export".into());
+    }
+
+    let dir = std::env::current_dir()?;
+    let (config, mods_dir) = pack_config(config, &dir, args)?;
+
+    if mdget::pack::loader_dependency(&config.loader).is_none() {
+        return Err(format!("Packs can't use the {} loader", config.loader).into());
+    }
+
+    let loader_version = loader_version(&config)?;
+
+    let tracked = tracked_files(sources, state, &dir, &mods_dir)?;
+
+    let sha256 = |data: &[u8]| mdget::packwiz::hash("sha256", data).unwrap_or_default();
+
+    // Relative path with / separators, as packwiz writes them
+    let relative = |path: &Path| {
+        path.strip_prefix(&dir)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+
+    let mut entries = BTreeMap::<String, IndexEntry>::new();
+    let mut metafiles = Vec::new();
+
+    std::fs::create_dir_all(&mods_dir)?;
+
+    for tracked in &tracked {
+        let side = match &tracked.project {
+            Some(project) if project.client_side == "unsupported" => "server",
+            Some(project) if project.server_side == "unsupported" => "client",
+            _ => "both",
+        };
+
+        let meta = ModToml {
+            name: tracked
+                .project
+                .as_ref()
+                .map(|project| project.title.clone())
+                .unwrap_or_else(|| tracked.install.slug.clone()),
+            filename: tracked.install.filename.clone(),
+            side: side.to_string(),
+            download: ModDownload {
+                url: tracked.file.url.clone(),
+                hash_format: "sha512".to_string(),
+                hash: tracked.install.sha512.clone(),
+            },
+            option: None,
+            update: match tracked.install.project_id.split_once(':') {
+                None => Some(ModUpdate {
+                    modrinth: Some(ModrinthUpdate {
+                        mod_id: tracked.install.project_id.clone(),
+                        version: tracked.install.version_id.clone(),
+                    }),
+                    ..Default::default()
+                }),
+                Some((mdget::curseforge::CURSEFORGE, project_id)) => Some(ModUpdate {
+                    curseforge: Some(CurseForgeUpdate {
+                        project_id: project_id.parse()?,
+                        file_id: tracked.install.version_id.parse()?,
+                    }),
+                    ..Default::default()
+                }),
+                Some(_) => None,
+            },
+        };
+
+        let content = toml::to_string(&meta)?;
+
+        // Numbered if a mod of the same source with the same slug already took the name
+        let name = metafile_name(&tracked.install);
+        let mut path = mods_dir.join(format!("{}{}", name, METAFILE_EXTENSION));
+
+        for n in 2.. {
+            if !entries.contains_key(&relative(&path)) {
+                break;
+            }
+
+            path = mods_dir.join(format!("{}-{}{}", name, n, METAFILE_EXTENSION));
+        }
+
+        std::fs::write(&path, &content)?;
+        metafiles.push(path.clone());
+
+        entries.insert(
+            relative(&path),
+            IndexEntry {
+                file: relative(&path),
+                hash: sha256(content.as_bytes()),
+                hash_format: None,
+                metafile: true,
+            },
+        );
+    }
+
+    let old_index = match std::fs::read(dir.join(mdget::packwiz::INDEX_FILE)) {
+        Ok(data) => Some(parse_packwiz::<IndexToml>(
+            mdget::packwiz::INDEX_FILE,
+            &data,
+        )?),
+        Err(_) => None,
+    };
+
+    for old in old_index.map(|index| index.files).unwrap_or_default() {
+        let path = dir.join(&old.file);
+
+        if entries.contains_key(&old.file) || !mdget::pack::inside(Path::new(&old.file)) {
+            continue;
+        }
+
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            // Removed since
+            Err(_) => continue,
+        };
+
+        // mdget wrote it for a mod that isn't tracked anymore. Metafiles added with packwiz or
+        // on another machine are kept.
+        if old.metafile && state.wrote_metafile(&path) {
+            mdget::cinfoln(format!("Removing {}", style(&old.file).red()).as_str());
+            std::fs::remove_file(&path)?;
+            continue;
+        }
+
+        entries.insert(
+            old.file.clone(),
+            IndexEntry {
+                hash: sha256(&data),
+                hash_format: None,
+                ..old
+            },
+        );
+    }
+
+    // Jars copied from disk have nowhere to be downloaded from, so the pack carries them
+    for install in state.installs_in(&mods_dir).filter(|i| is_local(i)) {
+        let path = mods_dir.join(&install.filename);
+        let data = std::fs::read(&path)?;
+
+        entries.insert(
+            relative(&path),
+            IndexEntry {
+                file: relative(&path),
+                hash: sha256(&data),
+                hash_format: None,
+                metafile: false,
+            },
+        );
+    }
+
+    for override_dir in &config.pack_overrides {
+        let path = dir.join(override_dir);
+
+        if !path.exists() {
+            mdget::cwarnln(format!("Skipping missing {}", style(override_dir).yellow()).as_str());
+            continue;
+        }
+
+        for file in mdget::packwiz::files_in(&path)? {
+            let data = std::fs::read(&file)?;
+
+            entries.insert(
+                relative(&file),
+                IndexEntry {
+                    file: relative(&file),
+                    hash: sha256(&data),
+                    hash_format: None,
+                    metafile: false,
+                },
+            );
+        }
+    }
+
+    let index = IndexToml {
+        hash_format: "sha256".to_string(),
+        files: entries.into_values().collect(),
+    };
+
+    let index_content = toml::to_string(&index)?;
+    std::fs::write(dir.join(mdget::packwiz::INDEX_FILE), &index_content)?;
+
+    // Keep what's in an existing pack.toml unless told otherwise
+    let old_pack = match std::fs::read(dir.join(PACK_FILE)) {
+        Ok(data) => Some(parse_packwiz::<PackToml>(PACK_FILE, &data)?),
+        Err(_) => None,
+    };
+
+    let name = option(args, "--name")
+        .map(|name| name.to_string())
+        .or_else(|| old_pack.as_ref().map(|pack| pack.name.clone()))
+        .unwrap_or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "mdget pack".to_string())
+        });
+
+    let pack = PackToml {
+        name,
+        author: option(args, "--author")
+            .map(|author| author.to_string())
+            .or_else(|| old_pack.as_ref().and_then(|pack| pack.author.clone())),
+        version: option(args, "--version")
+            .map(|version| version.to_string())
+            .or_else(|| old_pack.as_ref().and_then(|pack| pack.version.clone())),
+        description: old_pack.as_ref().and_then(|pack| pack.description.clone()),
+        pack_format: mdget::packwiz::PACK_FORMAT.to_string(),
+        index: IndexRef {
+            file: mdget::packwiz::INDEX_FILE.to_string(),
+            hash_format: "sha256".to_string(),
+            hash: sha256(index_content.as_bytes()),
+        },
+        versions: BTreeMap::from([
+            ("minecraft".to_string(), config.version.clone()),
+            (config.loader.clone(), loader_version),
+        ]),
+    };
+
+    std::fs::write(dir.join(PACK_FILE), toml::to_string(&pack)?)?;
+
+    state.record_metafiles(&dir, &metafiles);
+    state.save()?;
+
+    mdget::cinfoln(
+        format!(
+            "Wrote {} with {} mods and {} files",
+            PACK_FILE,
+            tracked.len(),
+            index.files.len()
+        )
+        .as_str(),
+    );
+
+    Ok(())
+}
+
+// A mod mdget tracks, the file it was installed from and its project
+struct TrackedFile {
+    install: Install,
+    file: File,
+    project: Option<ModrinthProject>,
+}
+
+// Whether an install was copied from a jar on disk
+fn is_local(install: &Install) -> bool {
+    install
+        .project_id
+        .strip_prefix(mdget::direct::LOCAL)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+// The config for packing up dir, with the manifest and --loader-version / --override applied,
+// and the directory its mods are in
+fn pack_config(config: &Config, dir: &Path, args: &[String]) -> mdget::Result<(Config, PathBuf)> {
+    let (mut config, mods_dir) = match Manifest::load(dir)? {
+        Some(manifest) => (manifest.config(config), manifest.mods_dir(dir)),
+        None if dir.join("mods").is_dir() => (config.clone(), dir.join("mods")),
+        None => (config.clone(), dir.to_path_buf()),
+    };
+
+    if let Some(loader_version) = option(args, "--loader-version") {
+        config.loader_version = Some(loader_version.to_string());
+    }
+
+    let overrides = options(args, "--override");
+
+    if !overrides.is_empty() {
+        config.pack_overrides = overrides.iter().map(|o| o.to_string()).collect();
+    }
+
+    Ok((config, mods_dir))
+}
+
+fn loader_version(config: &Config) -> Result<String, Box<dyn std::error::Error>> {
+    match &config.loader_version {
+        Some(loader_version) => Ok(loader_version.clone()),
+        None => Err(format!(
+            "No {} version set, pass --loader-version or set loader_version in the config",
+            config.loader
+        )
+        .into()),
+    }
+}
+
+// The mods tracked in mods_dir with the files they came from. The lockfile in dir knows the
+// URLs and sha1 hashes already, anything else is asked from the mod's source. Jars copied
+// from disk can't be downloaded and are left out.
+fn tracked_files(
+    sources: &Sources,
+    state: &State,
+    dir: &Path,
+    mods_dir: &Path,
+) -> Result<Vec<TrackedFile>, Box<dyn std::error::Error>> {
+    let installs = state
+        .installs_in(mods_dir)
+        .filter(|i| !is_local(i))
+        .cloned()
+        .collect::<Vec<_>>();
+    let lock = Lockfile::load(dir)?;
+
+    let mut ids = installs
+        .iter()
+        .map(|i| i.project_id.clone())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+
+    // Modrinth projects in one request, the other sources' one at a time
+    let (modrinth, others): (Vec<_>, Vec<_>) = ids.into_iter().partition(|id| !id.contains(':'));
+
+    let mut projects = if modrinth.is_empty() {
+        Vec::new()
+    } else {
+        sources.client().projects(&modrinth)?
+    };
+
+    for id in others {
+        projects.push(sources.project(&id)?);
+    }
+
+    let mut tracked = Vec::new();
+
+    for install in installs {
+        let locked = lock.as_ref().and_then(|lock| {
+            lock.mods
+                .iter()
+                .find(|m| m.version_id == install.version_id && m.hashes.sha512 == install.sha512)
+        });
+
+        let mut file = match locked {
+            Some(locked) => locked.file(),
+            None => sources
+                .version(&install.project_id, &install.version_id)?
+                .files
+                .into_iter()
+                .find(|f| f.hashes.sha512 == install.sha512 || f.filename == install.filename)
+                .ok_or_else(|| mdget::Error::NoFiles {
+                    project: install.slug.clone(),
+                })?,
+        };
+
+        // Sources that only give a sha1 learn the sha512 on install, the ones that give neither
+        // or no size have the jar looked at
+        file.hashes.sha512 = install.sha512.clone();
+
+        let path = mods_dir.join(&install.filename);
+
+        if file.hashes.sha1.is_empty() {
+            file.hashes = mdget::hash_file(&path)?;
+        }
+
+        if file.size <= 0 {
+            file.size = std::fs::metadata(&path)?.len() as i64;
+        }
+
+        let project = projects
+            .iter()
+            .find(|p| p.id == install.project_id)
+            .cloned();
+
+        tracked.push(TrackedFile {
+            install,
+            file,
+            project,
+        });
+    }
+
+    Ok(tracked)
+}
+
+const PACK_EXPORT_OPTIONS: &[&str] = &["--name", "--version", "--loader-version", "--override"];
+
+// mdget pack export <file.mrpack> [--name <name>] [--version <version>]
+//     [--loader-version <version>] [--override <dir>...]
+// Packs up the mods mdget tracks in the current directory (or its manifest's mods directory)
+fn pack_export(
+    sources: &Sources,
+    config: &Config,
+    state: &State,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = match positional(&args[3..], PACK_EXPORT_OPTIONS).first() {
+        Some(file) => PathBuf::from(file),
+        None => {
+            return Err("Usage: mdget pack export <file.mrpack>".into());
+        }
+    };
+
+    let dir = std::env::current_dir()?;
+    let (config, mods_dir) = pack_config(config, &dir, args)?;
+
+    let loader = match mdget::pack::loader_dependency(&config.loader) {
+        Some(loader) => loader,
+        None => return Err(format!("Packs can't use the {} loader", config.loader).into()),
+    };
+
+    let loader_version = loader_version(&config)?;
+
+    let tracked = tracked_files(sources, state, &dir, &mods_dir)?;
+
+    // required, optional or unsupported, unknown means we can't tell so require it
+    let side = |side: &str| match side {
+        "optional" | "unsupported" => side.to_string(),
+        _ => "required".to_string(),
+    };
+
+    // Packs may only download from a few hosts, files from anywhere else are shipped in the
+    // pack
+    let (downloadable, hosted_elsewhere): (Vec<_>, Vec<_>) = tracked
+        .iter()
+        .partition(|tracked| mdget::pack::allowed_download(&tracked.file.url));
+
+    let files = downloadable
+        .iter()
+        .map(|tracked| PackFile {
+            path: format!("mods/{}", tracked.install.filename),
+            hashes: tracked.file.hashes.clone(),
+            env: tracked.project.as_ref().map(|project| PackEnv {
+                client: side(&project.client_side),
+                server: side(&project.server_side),
+            }),
+            downloads: vec![tracked.file.url.clone()],
+            file_size: tracked.file.size,
+        })
+        .collect::<Vec<_>>();
+
+    let name = option(args, "--name")
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "mdget pack".to_string())
+        });
+
+    let index = PackIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: option(args, "--version").unwrap_or("1.0.0").to_string(),
+        name,
+        summary: None,
+        files,
+        dependencies: BTreeMap::from([
+            ("minecraft".to_string(), config.version.clone()),
+            (loader.to_string(), loader_version),
+        ]),
+    };
+
+    let mut writer = PackWriter::create(&file)?;
+    let mut added = 0;
+
+    for override_dir in &config.pack_overrides {
+        let path = dir.join(override_dir);
+
+        if !path.exists() {
+            mdget::cwarnln(format!("Skipping missing {}", style(override_dir).yellow()).as_str());
+            continue;
+        }
+
+        added += writer.add_override(Path::new(override_dir), &path)?;
+    }
+
+    // Jars mdget doesn't know the origin of, copied from disk or hosted elsewhere can't be
+    // downloaded, so ship them in the pack
+    for entry in std::fs::read_dir(&mods_dir)? {
+        let path = entry?.path();
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "jar") {
+            continue;
+        }
+
+        if downloadable.iter().any(|t| t.install.filename == filename) {
+            continue;
+        }
+
+        if hosted_elsewhere
+            .iter()
+            .any(|t| t.install.filename == filename)
+        {
+            mdget::cwarnln(
+                format!(
+                    "{} isn't hosted anywhere packs can download from, adding it to the overrides",
+                    style(&filename).yellow()
+                )
+                .as_str(),
+            );
+        } else {
+            mdget::cwarnln(
+                format!(
+                    "{} can't be downloaded, adding it to the overrides",
+                    style(&filename).yellow()
+                )
+                .as_str(),
+            );
+        }
+
+        added += writer.add_override(&Path::new("mods").join(&*filename), &path)?;
+    }
+
+    writer.finish(&index)?;
+
+    mdget::cinfoln(
+        format!(
+            "Exported {} with {} mods and {} override files to {}",
+            style(&index.name).cyan(),
+            index.files.len(),
+            added,
+            style(file.display()).cyan()
+        )
+        .as_str(),
+    );
+
+    Ok(())
+}
+
+// Slugs of the locked mods with the given project ids
+fn dependents(lock: &Lockfile, project_ids: &[String]) -> String {
+    lock.mods
+        .iter()
+        .filter(|m| project_ids.contains(&m.project_id))
+        .map(|m| m.slug.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Remove mods installed outside of a project, along with the dependencies nothing else needs
+fn remove(
+    state: &mut State,
+    dir: &Path,
+    targets: &[&str],
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut removed = Vec::new();
+
+    for target in targets {
+        let install = match state.find(dir, target) {
+            Some(install) => install,
+            None => return Err(format!("{} isn't installed here!", target).into()),
+        };
+
+        // Dependents that are staying
+        let dependents = state
+            .installs_in(dir)
+            .filter(|i| install.required_by.contains(&i.project_id))
+            .filter(|i| {
+                !targets.contains(&i.slug.as_str()) && !targets.contains(&i.project_id.as_str())
+            })
+            .map(|i| i.slug.as_str())
+            .collect::<Vec<_>>();
+
+        if !dependents.is_empty() && !force {
+            return Err(format!(
+                "{} is required by {}! Use --force to remove it anyway",
+                target,
+                dependents.join(", ")
+            )
+            .into());
+        }
+
+        removed.push(install.project_id.clone());
+    }
+
+    for install in state.orphans(dir, &removed) {
+        mdget::cinfoln(
+            format!(
+                "Removing {}{}",
+                style(&install.filename).red(),
+                if removed.contains(&install.project_id) {
+                    ""
+                } else {
+                    " (no longer needed)"
+                }
+            )
+            .as_str(),
+        );
+
+        if let Err(err) = std::fs::remove_file(install.path()) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+
+        state.forget(dir, &install.filename);
+    }
+
+    state.save()?;
+
+    Ok(())
+}
+
+// Install everything declared in the manifest and remove jars mdget installed that the
+// manifest no longer declares
+fn sync(
+    sources: &Sources,
+    config: &Config,
+    manifest: &Manifest,
+    dir: &Path,
+    args: &[String],
+    state: &mut State,
+    update: Option<&[String]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mods_dir = manifest.mods_dir(dir);
+
+    if !mods_dir.exists() {
+        std::fs::create_dir_all(&mods_dir)?;
+    }
+
+    mdget::cinfoln(
+        format!(
+            "Syncing {} mods for {} ({})...",
+            manifest.mods.len(),
+            style(&config.version).cyan(),
+            style(&config.loader).cyan()
+        )
+        .as_str(),
+    );
+
+    let (mut lock, mut failed) = match Lockfile::load(dir)? {
+        Some(lock) if update.is_none() && lock.matches(manifest) => {
+            mdget::cinfoln(format!("Installing from {}", mdget::lock::LOCK_FILE).as_str());
+            (lock, Vec::new())
+        }
+        previous => lock_manifest(
+            sources,
+            config,
+            manifest,
+            previous.as_ref(),
+            dir,
+            args,
+            update,
+        )?,
+    };
+
+    let unresolved = !failed.is_empty();
+
+    let mut wanted = HashSet::new();
+    let mut downloads = Vec::new();
+    // Whether sha512s were learned for files whose source only gave a sha1
+    let mut learned = false;
+
+    for (i, locked) in lock.mods.iter_mut().enumerate() {
+        wanted.insert(locked.filename.clone());
+
+        let path = mods_dir.join(&locked.filename);
+
+        let hashes = match path.exists() {
+            true => Some(mdget::hash_file(&path)?),
+            false => None,
+        };
+
+        if let Some(hashes) = hashes.filter(|hashes| locked.hashes.matches(hashes)) {
+            if locked.hashes.sha512.is_empty() {
+                locked.hashes = hashes;
+                learned = true;
+            }
+
+            mdget::cinfoln(format!("{} is up to date", style(&locked.slug).cyan()).as_str());
+
+            // Keep the original install time of files we already know about
+            let known = state
+                .installs_in(&mods_dir)
+                .any(|i| i.filename == locked.filename && i.version_id == locked.version_id);
+
+            if !known {
+                state.record(Install::new(&mods_dir, locked));
+            }
+
+            continue;
+        }
+
+        downloads.push(i);
+    }
+
+    let files = downloads
+        .iter()
+        .map(|&i| lock.mods[i].file())
+        .collect::<Vec<_>>();
+    let results = download_all(sources.client(), config, &files, &mods_dir);
+
+    for (i, result) in downloads.into_iter().zip(results) {
+        let locked = &mut lock.mods[i];
+
+        match result {
+            Ok(hashes) => {
+                learned |= locked.hashes.sha512.is_empty();
+                locked.hashes = hashes;
+                state.record(Install::new(&mods_dir, locked));
+            }
+            Err(err) => failed.push((locked.slug.clone(), err)),
+        }
+    }
+
+    if learned && !unresolved {
+        lock.save(dir)?;
+    }
+
+    // Don't remove anything if we couldn't work out the full set of files
+    if unresolved {
+        mdget::cwarnln("Skipping cleanup, not every mod could be resolved");
+        state.save()?;
+        return report_failures(&failed);
+    }
+
+    // Only jars mdget installed itself are removed, anything else in the mods dir is kept
+    let unwanted = state
+        .installs_in(&mods_dir)
+        .filter(|install| !wanted.contains(&install.filename))
+        .map(|install| install.filename.clone())
+        .collect::<Vec<_>>();
+
+    for filename in unwanted {
+        let path = mods_dir.join(&filename);
+
+        if path.is_file() {
+            mdget::cinfoln(format!("Removing {}", style(&filename).red()).as_str());
+            std::fs::remove_file(&path)?;
+        }
+
+        state.forget(&mods_dir, &filename);
+    }
+
+    state.save()?;
+
+    report_failures(&failed)?;
+
+    mdget::cinfoln("Sync complete!");
+
+    Ok(())
+}
+
+// Resolve the manifest and write mdget.lock. Mods that were already locked keep their
+// version unless their requirement changed or they are being updated (all of them if
+// update is empty). The lockfile is only written if every mod resolved.
+fn lock_manifest(
+    sources: &Sources,
+    config: &Config,
+    manifest: &Manifest,
+    previous: Option<&Lockfile>,
+    dir: &Path,
+    args: &[String],
+    update: Option<&[String]>,
+) -> Result<(Lockfile, Failures), Box<dyn std::error::Error>> {
+    let mut resolver = Resolver::new(sources, config).on_optional(optional_prompt(args));
+
+    if let Some(previous) = previous {
+        if previous.version == manifest.version && previous.loader == manifest.loader {
+            for locked in &previous.mods {
+                let ids = [&locked.slug, &locked.project_id];
+
+                let changed = ids
+                    .iter()
+                    .any(|modid| manifest.mods.get(*modid) != previous.requested.get(*modid));
+
+                let updating = match update {
+                    Some([]) => true,
+                    Some(only) => ids.iter().any(|id| only.contains(id)),
+                    None => false,
+                };
+
+                if !changed && !updating {
+                    resolver.prefer(&locked.project_id, &locked.version_id);
+                }
+            }
+        }
+    }
+
+    mdget::cinfoln("Preparing to query Modrinth API...");
+
+    let resolution = resolver.resolve(&manifest.mods);
+
+    check_conflicts(&resolution.conflicts)?;
+
+    let mut lock = Lockfile::new(manifest);
+
+    lock.mods = resolution.mods.iter().filter_map(LockedMod::new).collect();
+
+    // Keep the hashes recorded on download for files whose source doesn't know them, so a
+    // file that changed behind the same URL is noticed
+    for locked in lock.mods.iter_mut().filter(|m| m.hashes.sha512.is_empty()) {
+        let old = previous.into_iter().flat_map(|p| &p.mods).find(|m| {
+            m.project_id == locked.project_id
+                && m.version_id == locked.version_id
+                && m.filename == locked.filename
+        });
+
+        if let Some(old) = old {
+            locked.hashes = old.hashes.clone();
+        }
+    }
+
+    if resolution.failed.is_empty() {
+        lock.save(dir)?;
+        mdget::cinfoln(format!("Wrote {}", mdget::lock::LOCK_FILE).as_str());
+    }
+
+    Ok((lock, resolution.failed))
+}
+
+// Print which projects failed and why, and fail the command if any did
+fn report_failures(failed: &Failures) -> Result<(), Box<dyn std::error::Error>> {
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    for (project, err) in failed {
+        mdget::cerrorln(format!("{}: {}", style(project).red(), err).as_str());
+    }
+
+    Err(format!("Failed to install {} mods", failed.len()).into())
+}
+
+// Explain every conflict and refuse to go any further
+fn check_conflicts(conflicts: &[Conflict]) -> Result<(), Box<dyn std::error::Error>> {
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    for conflict in conflicts {
+        for line in conflict.to_string().lines() {
+            mdget::cerrorln(line);
+        }
+    }
+
+    Err(format!("Found {} conflicts, nothing was installed", conflicts.len()).into())
+}
+
+// Decides on optional dependencies: --optional installs all of them, --no-optional none,
+// otherwise ask if we're attached to a terminal
+fn optional_prompt(args: &[String]) -> impl FnMut(&ModrinthProject, &ModrinthProject) -> bool {
+    let all = args.iter().any(|arg| arg == "--optional");
+    let none = args.iter().any(|arg| arg == "--no-optional");
+
+    move |parent, dependency| {
+        if all || none {
+            return all;
+        }
+
+        let term = Term::stdout();
+
+        if !term.is_term() {
+            mdget::cinfoln(
+                format!(
+                    "Skipping optional dependency {} of {}",
+                    style(&dependency.title).cyan(),
+                    style(&parent.title).cyan()
+                )
+                .as_str(),
+            );
+            return false;
+        }
+
+        mdget::cinfo(
+            format!(
+                "Install optional dependency {} of {}? [y/N] ",
+                style(&dependency.title).cyan(),
+                style(&parent.title).cyan()
+            )
+            .as_str(),
+        );
+
+        let _ = std::io::stdout().flush();
+
+        matches!(
+            term.read_line().unwrap_or_default().trim(),
+            "y" | "Y" | "yes"
+        )
+    }
+}
+
+fn flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|arg| arg == name)
+}
+
+// Remove every --name, returning whether there was one
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != name);
+    args.len() != len
+}
+
+// Remove every --name and the value following it, returning the values
+fn take_options(args: &mut Vec<String>, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+
+    while let Some(i) = args.iter().position(|arg| arg == name) {
+        args.remove(i);
+
+        if i < args.len() {
+            values.push(args.remove(i));
+        }
+    }
+
+    values
+}
+
+// Value following the first --name
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    options(args, name).into_iter().next()
+}
+
+// Values following every --name
+fn options<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == name)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
+
+// Arguments that aren't flags or the values of the given options
+fn positional<'a>(args: &'a [String], with_values: &[&str]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut skip = false;
+
+    for arg in args {
+        if std::mem::take(&mut skip) {
+            continue;
+        }
+
+        if arg.starts_with('-') {
+            skip = with_values.contains(&arg.as_str());
+            continue;
+        }
+
+        positional.push(arg.as_str());
+    }
+
+    positional
+}
@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Config, Error, Result};
+
+pub const MANIFEST_FILE: &str = "mdget.toml";
+
+// Version requirement meaning "newest version for the target game version and loader"
+pub const LATEST: &str = "*";
+
+// Per-directory project manifest (mdget.toml)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    pub loader: String,
+    #[serde(default = "default_mods_dir")]
+    pub mods_dir: String,
+    // Overrides the loader version and pack override directories of the global config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pack_overrides: Vec<String>,
+    // slug or project id -> "*" or a version number / version id
+    #[serde(default)]
+    pub mods: BTreeMap<String, String>,
+}
+
+fn default_mods_dir() -> String {
+    "mods".to_string()
+}
+
+impl Manifest {
+    // Start a new manifest targeting the same version and loader as the global config
+    pub fn new(config: &Config) -> Self {
+        Manifest {
+            version: config.version.clone(),
+            loader: config.loader.clone(),
+            mods_dir: default_mods_dir(),
+            loader_version: None,
+            pack_overrides: Vec::new(),
+            mods: BTreeMap::new(),
+        }
+    }
+
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(MANIFEST_FILE)
+    }
+
+    // Returns Ok(None) if the directory has no manifest
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(dir);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+
+        match toml::from_str::<Manifest>(&content) {
+            Ok(data) => Ok(Some(data)),
+            Err(source) => Err(Error::Parse { path, source }),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let toml = toml::to_string(self)?;
+
+        std::fs::write(Self::path(dir), toml)?;
+
+        Ok(())
+    }
+
+    // The global config with version and loader (and the pack settings, if set) overridden
+    // by the manifest
+    pub fn config(&self, base: &Config) -> Config {
+        let mut config = base.clone();
+        config.version = self.version.clone();
+        config.loader = self.loader.clone();
+
+        // The global loader version is meaningless for another loader
+        if self.loader_version.is_some() || self.loader != base.loader {
+            config.loader_version = self.loader_version.clone();
+        }
+
+        if !self.pack_overrides.is_empty() {
+            config.pack_overrides = self.pack_overrides.clone();
+        }
+
+        config
+    }
+
+    pub fn mods_dir(&self, dir: &Path) -> PathBuf {
+        dir.join(&self.mods_dir)
+    }
+}
@@ -0,0 +1,406 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Error, File, Hashes, Result};
+
+pub const INDEX_FILE: &str = "modrinth.index.json";
+
+// Folders in the archive copied into the instance, the side specific one after the shared one
+pub const OVERRIDES: &str = "overrides";
+pub const CLIENT_OVERRIDES: &str = "client-overrides";
+pub const SERVER_OVERRIDES: &str = "server-overrides";
+
+// The hosts files of a pack may be downloaded from
+pub const ALLOWED_HOSTS: &[&str] = &[
+    "cdn.modrinth.com",
+    "github.com",
+    "raw.githubusercontent.com",
+    "gitlab.com",
+];
+
+// modrinth.index.json of a .mrpack
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<PackFile>,
+    // minecraft, fabric-loader, quilt-loader, forge or neoforge -> version
+    pub dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackFile {
+    // Relative to the instance directory, e.g. mods/sodium.jar
+    pub path: String,
+    pub hashes: Hashes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<PackEnv>,
+    pub downloads: Vec<String>,
+    pub file_size: i64,
+}
+
+// required, optional or unsupported on each side
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackEnv {
+    pub client: String,
+    pub server: String,
+}
+
+impl PackFile {
+    // Whether the file is required, optional or unsupported on a side (client or server).
+    // Files without env are required everywhere.
+    pub fn support(&self, side: &str) -> &str {
+        match (&self.env, side) {
+            (Some(env), "client") => &env.client,
+            (Some(env), "server") => &env.server,
+            _ => "required",
+        }
+    }
+
+    // The path relative to the instance directory, None if it would escape it
+    pub fn relative_path(&self) -> Option<PathBuf> {
+        let path = Path::new(&self.path);
+
+        inside(path).then(|| path.to_path_buf())
+    }
+
+    // The path relative to the instance directory if the file can be installed: it stays
+    // inside the instance, has the sha1 and sha512 the format requires and is only downloaded
+    // from allowed hosts
+    pub fn check(&self, pack: &str) -> Result<PathBuf> {
+        let path = self.relative_path().ok_or_else(|| Error::UnsafePath {
+            pack: pack.to_string(),
+            path: self.path.clone(),
+        })?;
+
+        if self.hashes.sha1.is_empty() || self.hashes.sha512.is_empty() {
+            return Err(Error::UnhashedFile {
+                pack: pack.to_string(),
+                path: self.path.clone(),
+            });
+        }
+
+        if self.downloads.is_empty() {
+            return Err(Error::NoFiles {
+                project: self.path.clone(),
+            });
+        }
+
+        if let Some(url) = self.downloads.iter().find(|url| !allowed_download(url)) {
+            return Err(Error::UnsafeDownload {
+                pack: pack.to_string(),
+                url: url.clone(),
+            });
+        }
+
+        Ok(path)
+    }
+
+    // The file to download into the directory its path is in, from the first download and the
+    // others if that fails
+    pub fn file(&self) -> File {
+        File {
+            hashes: self.hashes.clone(),
+            url: self.downloads.first().cloned().unwrap_or_default(),
+            mirrors: self.downloads.iter().skip(1).cloned().collect(),
+            local: None,
+            filename: Path::new(&self.path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            primary: true,
+            size: self.file_size,
+            file_type: None,
+        }
+    }
+}
+
+// The dependencies key for a loader, e.g. fabric -> fabric-loader
+pub fn loader_dependency(loader: &str) -> Option<&'static str> {
+    match loader {
+        "fabric" => Some("fabric-loader"),
+        "quilt" => Some("quilt-loader"),
+        "forge" => Some("forge"),
+        "neoforge" => Some("neoforge"),
+        _ => None,
+    }
+}
+
+// Whether a pack may list url as a download, an https URL on one of the allowed hosts
+pub fn allowed_download(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| ALLOWED_HOSTS.contains(&host))
+    })
+}
+
+// Whether a relative path stays inside the directory it is joined to
+pub fn inside(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// An opened .mrpack archive
+pub struct Pack {
+    path: PathBuf,
+    archive: zip::ZipArchive<std::fs::File>,
+    pub index: PackIndex,
+}
+
+impl Pack {
+    pub fn open(path: &Path) -> Result<Self> {
+        let zip_error = |source| Error::Zip {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?).map_err(zip_error)?;
+
+        let index = archive.by_name(INDEX_FILE).map_err(zip_error)?;
+
+        let index = serde_json::from_reader(index).map_err(|source| Error::Json {
+            path: path.join(INDEX_FILE),
+            source,
+        })?;
+
+        Ok(Pack {
+            path: path.to_path_buf(),
+            archive,
+            index,
+        })
+    }
+
+    // Copy the overrides into dir, then the overrides for side (client or server) over them.
+    // Returns the number of files written.
+    pub fn extract_overrides(&mut self, dir: &Path, side: &str) -> Result<usize> {
+        let side_overrides = match side {
+            "server" => SERVER_OVERRIDES,
+            _ => CLIENT_OVERRIDES,
+        };
+
+        let mut written = 0;
+
+        for prefix in [OVERRIDES, side_overrides] {
+            for i in 0..self.archive.len() {
+                let mut entry = self.archive.by_index(i).map_err(|source| Error::Zip {
+                    path: self.path.clone(),
+                    source,
+                })?;
+
+                // Skips entries that would end up outside of dir
+                let relative = match Path::new(entry.name()).strip_prefix(prefix) {
+                    Ok(relative) if inside(relative) => relative.to_path_buf(),
+                    _ => continue,
+                };
+
+                let target = dir.join(relative);
+
+                if entry.is_dir() {
+                    std::fs::create_dir_all(&target)?;
+                    continue;
+                }
+
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                std::io::copy(&mut entry, &mut std::fs::File::create(&target)?)?;
+
+                written += 1;
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+// Writes a .mrpack next to its destination and moves it into place once it is complete
+pub struct PackWriter {
+    path: PathBuf,
+    tmp: PathBuf,
+    zip: zip::ZipWriter<std::fs::File>,
+}
+
+impl PackWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let tmp = path.with_extension("mrpack.tmp");
+
+        Ok(PackWriter {
+            path: path.to_path_buf(),
+            zip: zip::ZipWriter::new(std::fs::File::create(&tmp)?),
+            tmp,
+        })
+    }
+
+    // Add a file, or everything in a directory, under overrides/ at relative.
+    // Returns the number of files added.
+    pub fn add_override(&mut self, relative: &Path, source: &Path) -> Result<usize> {
+        if source.is_dir() {
+            let mut added = 0;
+
+            for entry in std::fs::read_dir(source)? {
+                let entry = entry?;
+
+                added += self.add_override(&relative.join(entry.file_name()), &entry.path())?;
+            }
+
+            return Ok(added);
+        }
+
+        let name = Path::new(OVERRIDES)
+            .join(relative)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        self.zip
+            .start_file(name, zip::write::FileOptions::default())
+            .map_err(|source| self.zip_error(source))?;
+
+        std::io::copy(&mut std::fs::File::open(source)?, &mut self.zip)?;
+
+        Ok(1)
+    }
+
+    // Write the index and move the pack into place
+    pub fn finish(mut self, index: &PackIndex) -> Result<()> {
+        let json = serde_json::to_string_pretty(index).map_err(|source| Error::Json {
+            path: self.path.join(INDEX_FILE),
+            source,
+        })?;
+
+        self.zip
+            .start_file(INDEX_FILE, zip::write::FileOptions::default())
+            .map_err(|source| self.zip_error(source))?;
+
+        self.zip.write_all(json.as_bytes())?;
+
+        self.zip.finish().map_err(|source| self.zip_error(source))?;
+
+        std::fs::rename(&self.tmp, &self.path)?;
+
+        Ok(())
+    }
+
+    fn zip_error(&self, source: zip::result::ZipError) -> Error {
+        Error::Zip {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inside_rejects_escaping_paths() {
+        assert!(inside(Path::new("mods/sodium.jar")));
+        assert!(inside(Path::new("config/a/b.toml")));
+
+        assert!(!inside(Path::new("")));
+        assert!(!inside(Path::new("../mods/sodium.jar")));
+        assert!(!inside(Path::new("mods/../../sodium.jar")));
+        assert!(!inside(Path::new("/etc/passwd")));
+        assert!(!inside(Path::new("./mods/sodium.jar")));
+    }
+
+    #[test]
+    fn check_rejects_unhashed_files_and_other_hosts() {
+        let pack_file = PackFile {
+            path: "mods/sodium.jar".to_string(),
+            hashes: Hashes {
+                sha1: "ab".to_string(),
+                sha512: "cd".to_string(),
+            },
+            downloads: vec!["https://cdn.modrinth.com/sodium.jar".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pack_file.check("pack").unwrap(),
+            PathBuf::from("mods/sodium.jar")
+        );
+
+        let mut unhashed = pack_file.clone();
+        unhashed.hashes.sha1.clear();
+
+        assert!(matches!(
+            unhashed.check("pack"),
+            Err(Error::UnhashedFile { .. })
+        ));
+
+        for url in [
+            "http://cdn.modrinth.com/sodium.jar",
+            "https://example.com/sodium.jar",
+        ] {
+            let mut elsewhere = pack_file.clone();
+            elsewhere.downloads.push(url.to_string());
+
+            assert!(matches!(
+                elsewhere.check("pack"),
+                Err(Error::UnsafeDownload { .. })
+            ));
+        }
+
+        let mut escaping = pack_file.clone();
+        escaping.path = "../sodium.jar".to_string();
+
+        assert!(matches!(
+            escaping.check("pack"),
+            Err(Error::UnsafePath { .. })
+        ));
+    }
+
+    #[test]
+    fn file_uses_other_downloads_as_mirrors() {
+        let pack_file = PackFile {
+            path: "mods/sodium.jar".to_string(),
+            downloads: vec![
+                "https://cdn.modrinth.com/sodium.jar".to_string(),
+                "https://github.com/sodium.jar".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let file = pack_file.file();
+
+        assert_eq!(file.filename, "sodium.jar");
+        assert_eq!(file.url, "https://cdn.modrinth.com/sodium.jar");
+        assert_eq!(file.mirrors, vec!["https://github.com/sodium.jar"]);
+    }
+
+    #[test]
+    fn allowed_download_hosts() {
+        assert!(allowed_download("https://cdn.modrinth.com/data/a/b.jar"));
+        assert!(allowed_download(
+            "https://github.com/o/r/releases/download/v1/a.jar"
+        ));
+
+        assert!(!allowed_download("http://cdn.modrinth.com/data/a/b.jar"));
+        assert!(!allowed_download(
+            "https://edge.forgecdn.net/files/1/2/a.jar"
+        ));
+        assert!(!allowed_download(
+            "https://cdn.modrinth.com.example.com/a.jar"
+        ));
+        assert!(!allowed_download("file:mods/a.jar"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, path::Path, path::PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+pub const PACK_FILE: &str = "pack.toml";
+pub const INDEX_FILE: &str = "index.toml";
+pub const METAFILE_EXTENSION: &str = ".pw.toml";
+pub const PACK_FORMAT: &str = "packwiz:1.1.0";
+
+// pack.toml
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackToml {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub pack_format: String,
+    pub index: IndexRef,
+    // minecraft and the loader (fabric, quilt, forge or neoforge) -> version
+    pub versions: BTreeMap<String, String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexRef {
+    // Relative to pack.toml
+    pub file: String,
+    pub hash_format: String,
+    pub hash: String,
+}
+
+// index.toml, every file of the pack with its hash
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexToml {
+    pub hash_format: String,
+    #[serde(default)]
+    pub files: Vec<IndexEntry>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexEntry {
+    // Relative to index.toml
+    pub file: String,
+    pub hash: String,
+    // Overrides the index's hash format for this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_format: Option<String>,
+    // The file is a .pw.toml describing a file to download
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metafile: bool,
+}
+
+// A .pw.toml metafile
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModToml {
+    pub name: String,
+    pub filename: String,
+    // client, server or both
+    #[serde(default = "default_side")]
+    pub side: String,
+    pub download: ModDownload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option: Option<ModOption>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<ModUpdate>,
+}
+
+fn default_side() -> String {
+    "both".to_string()
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModDownload {
+    // Empty for CurseForge files, which are downloaded through the CurseForge API
+    #[serde(default)]
+    pub url: String,
+    pub hash_format: String,
+    pub hash: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModOption {
+    pub optional: bool,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<ModrinthUpdate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<CurseForgeUpdate>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModrinthUpdate {
+    pub mod_id: String,
+    // Version id
+    pub version: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CurseForgeUpdate {
+    pub project_id: u64,
+    pub file_id: u64,
+}
+
+impl ModToml {
+    // Whether the file belongs on a side (client or server)
+    pub fn supports(&self, side: &str) -> bool {
+        self.side == "both" || self.side == side
+    }
+
+    pub fn optional(&self) -> bool {
+        self.option.as_ref().is_some_and(|option| option.optional)
+    }
+}
+
+// Hex digest of data in one of the hash formats packwiz uses. None for formats mdget can't
+// compute (md5, murmur2).
+pub fn hash(format: &str, data: &[u8]) -> Option<String> {
+    match format {
+        "sha1" => Some(format!("{:x}", Sha1::digest(data))),
+        "sha256" => Some(format!("{:x}", Sha256::digest(data))),
+        "sha512" => Some(format!("{:x}", Sha512::digest(data))),
+        _ => None,
+    }
+}
+
+// Every file under dir, recursively
+pub fn files_in(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(files_in(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,1129 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use console::style;
+
+use crate::{
+    manifest::LATEST, pool, Config, Dependency, Error, ModrinthProject, ProjectVersion,
+    ProjectVersions, Result, Sources,
+};
+
+// A project and the version of it that will be installed
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub project: ModrinthProject,
+    pub version: ProjectVersion,
+    // Explicitly requested rather than pulled in as a dependency
+    pub requested: bool,
+    // Ids of the resolved projects that depend on this one
+    pub required_by: Vec<String>,
+    // Slugs of the dependents it was first reached through, starting at a requested mod
+    pub chain: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub mods: Vec<Resolved>,
+    // Projects that couldn't be resolved and why
+    pub failed: Vec<(String, Error)>,
+    // Reasons the resolved mods can't be installed together
+    pub conflicts: Vec<Conflict>,
+}
+
+// A version pinned by the manifest or a dependent, and the chain of dependents pinning it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pin {
+    pub version: String,
+    pub chain: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    // Different versions of the same project are pinned, or the one picked isn't pinned
+    Version {
+        project: String,
+        pins: Vec<Pin>,
+        // The version that would be installed, if it isn't one of the pins
+        selected: Option<String>,
+    },
+    // A resolved version declares another resolved project incompatible
+    Incompatible {
+        project: String,
+        chain: Vec<String>,
+        incompatible: String,
+        incompatible_chain: Vec<String>,
+    },
+}
+
+// The slug a project is looked up by, with the same source prefix as its id so that e.g. a
+// CurseForge jei isn't mistaken for a Modrinth one
+fn slug_key(project: &ModrinthProject) -> String {
+    match project.id.split_once(':') {
+        Some((source, _)) => format!("{}:{}", source, project.slug),
+        None => project.slug.clone(),
+    }
+}
+
+fn display_chain(chain: &[String]) -> String {
+    if chain.is_empty() {
+        "requested".to_string()
+    } else {
+        format!("via {}", chain.join(" -> "))
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::Version {
+                project,
+                pins,
+                selected,
+            } => {
+                write!(f, "{} is pinned to different versions:", project)?;
+
+                for pin in pins {
+                    write!(f, "\n  {} ({})", pin.version, display_chain(&pin.chain))?;
+                }
+
+                if let Some(selected) = selected {
+                    write!(f, "\n  {} (selected)", selected)?;
+                }
+
+                Ok(())
+            }
+            Conflict::Incompatible {
+                project,
+                chain,
+                incompatible,
+                incompatible_chain,
+            } => write!(
+                f,
+                "{} ({}) is incompatible with {} ({})",
+                project,
+                display_chain(chain),
+                incompatible,
+                display_chain(incompatible_chain)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Requirement {
+    Latest,
+    // Version number or id given by the user
+    Version(String),
+    // Exact version id pinned by a dependent
+    VersionId(String),
+    // sha512 of the file to install, given as sha512:<hex>
+    Hash(String),
+}
+
+struct Pending {
+    project: String,
+    requirement: Requirement,
+    parent: Option<String>,
+    chain: Vec<String>,
+    requested: bool,
+}
+
+// A dependency declared incompatible by a resolved version
+struct Incompatibility {
+    declared_by: String,
+    project_id: String,
+    version_id: Option<String>,
+}
+
+// Prefix of requirements that pin a file by its sha512
+pub const HASH_PREFIX: &str = "sha512:";
+
+type OptionalFn<'a> = Box<dyn FnMut(&ModrinthProject, &ModrinthProject) -> bool + 'a>;
+
+// Recursively resolves mods through the dependencies of the versions that get picked
+pub struct Resolver<'a> {
+    sources: &'a Sources,
+    config: &'a Config,
+    optional: OptionalFn<'a>,
+    // project id -> version id to use instead of the latest one
+    preferred: HashMap<String, String>,
+    projects: HashMap<String, ModrinthProject>,
+    versions: HashMap<String, ProjectVersion>,
+    // project id -> its versions that support the game version and loader
+    supported_versions: HashMap<String, ProjectVersions>,
+    // project id -> whether to install it as an optional dependency, asked once even when
+    // resolving again
+    decided: HashMap<String, bool>,
+    // project id -> version id pinned by a dependent after another version was picked
+    forced: HashMap<String, String>,
+    // project id -> versions pinned for it
+    pins: BTreeMap<String, Vec<(String, Vec<String>)>>,
+    incompatibilities: Vec<Incompatibility>,
+    queue: VecDeque<Pending>,
+    resolution: Resolution,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(sources: &'a Sources, config: &'a Config) -> Self {
+        Resolver {
+            sources,
+            config,
+            optional: Box::new(|_, _| false),
+            preferred: HashMap::new(),
+            projects: HashMap::new(),
+            versions: HashMap::new(),
+            supported_versions: HashMap::new(),
+            decided: HashMap::new(),
+            forced: HashMap::new(),
+            pins: BTreeMap::new(),
+            incompatibilities: Vec::new(),
+            queue: VecDeque::new(),
+            resolution: Resolution::default(),
+        }
+    }
+
+    // Decide whether to install an optional dependency, given the dependent and the dependency.
+    // Optional dependencies are skipped by default.
+    pub fn on_optional(
+        mut self,
+        optional: impl FnMut(&ModrinthProject, &ModrinthProject) -> bool + 'a,
+    ) -> Self {
+        self.optional = Box::new(optional);
+        self
+    }
+
+    // Use this version of a project instead of the latest one, as long as nothing pins another
+    pub fn prefer(&mut self, project_id: &str, version_id: &str) {
+        self.preferred
+            .insert(project_id.to_string(), version_id.to_string());
+    }
+
+    // Resolve mods given as slug or project id -> "*" or a version number / version id
+    pub fn resolve(mut self, mods: &BTreeMap<String, String>) -> Resolution {
+        // A mod can be picked before a dependent pins another version of it, so resolve again
+        // with the pinned versions until they agree with the ones picked
+        loop {
+            self.resolve_all(mods);
+
+            if !self.force_pins() {
+                break;
+            }
+        }
+
+        self.check_conflicts();
+
+        self.resolution
+    }
+
+    fn resolve_all(&mut self, mods: &BTreeMap<String, String>) {
+        self.resolution = Resolution::default();
+        self.pins.clear();
+        self.incompatibilities.clear();
+
+        for (modid, requirement) in mods {
+            let requirement = if requirement == LATEST {
+                Requirement::Latest
+            } else if let Some(sha512) = requirement.strip_prefix(HASH_PREFIX) {
+                Requirement::Hash(sha512.to_lowercase())
+            } else {
+                Requirement::Version(requirement.to_string())
+            };
+
+            self.queue.push_back(Pending {
+                project: modid.to_string(),
+                requirement,
+                parent: None,
+                chain: Vec::new(),
+                requested: true,
+            });
+        }
+
+        // Resolve a level of the dependency tree at a time, fetching what it needs up front
+        while !self.queue.is_empty() {
+            self.prefetch();
+
+            for _ in 0..self.queue.len() {
+                if let Some(pending) = self.queue.pop_front() {
+                    self.resolve_pending(pending);
+                }
+            }
+        }
+    }
+
+    // Force the mods pinned to a single version other than the one picked to that version.
+    // Returns whether there were any.
+    fn force_pins(&mut self) -> bool {
+        let mut changed = false;
+
+        for (project_id, pins) in &self.pins {
+            let version_id = &pins[0].0;
+
+            if pins.iter().any(|(id, _)| id != version_id) || self.forced.contains_key(project_id) {
+                continue;
+            }
+
+            let picked = self
+                .resolution
+                .mods
+                .iter()
+                .find(|r| &r.project.id == project_id)
+                .and_then(|r| r.version.id.as_ref());
+
+            if picked.is_some_and(|picked| picked != version_id) {
+                self.forced.insert(project_id.clone(), version_id.clone());
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn check_conflicts(&mut self) {
+        for (project_id, pins) in &self.pins {
+            let mut versions = pins.iter().map(|(id, _)| id).collect::<Vec<_>>();
+            versions.sort();
+            versions.dedup();
+
+            let selected = self
+                .resolution
+                .mods
+                .iter()
+                .find(|r| &r.project.id == project_id)
+                .map(|r| &r.version)
+                .filter(|version| !version.id.as_ref().is_some_and(|id| versions.contains(&id)));
+
+            if versions.len() < 2 && selected.is_none() {
+                continue;
+            }
+
+            let project = match self.projects.get(project_id) {
+                Some(project) => project.title.clone(),
+                None => project_id.clone(),
+            };
+
+            let pins = pins
+                .iter()
+                .map(|(version_id, chain)| Pin {
+                    version: self
+                        .versions
+                        .get(version_id)
+                        .and_then(|v| v.version_number.clone())
+                        .unwrap_or_else(|| version_id.clone()),
+                    chain: chain.clone(),
+                })
+                .collect();
+
+            let selected = selected.map(|version| {
+                version
+                    .version_number
+                    .clone()
+                    .or_else(|| version.id.clone())
+                    .unwrap_or_default()
+            });
+
+            self.resolution.conflicts.push(Conflict::Version {
+                project,
+                pins,
+                selected,
+            });
+        }
+
+        for incompatibility in &self.incompatibilities {
+            let find = |id: &str| self.resolution.mods.iter().find(|r| r.project.id == id);
+
+            let (declared_by, target) = match (
+                find(&incompatibility.declared_by),
+                find(&incompatibility.project_id),
+            ) {
+                (Some(declared_by), Some(target)) => (declared_by, target),
+                _ => continue,
+            };
+
+            // Only a specific version of the project is incompatible
+            if let Some(version_id) = &incompatibility.version_id {
+                if target.version.id.as_ref() != Some(version_id) {
+                    continue;
+                }
+            }
+
+            self.resolution.conflicts.push(Conflict::Incompatible {
+                project: declared_by.project.title.clone(),
+                chain: declared_by.chain.clone(),
+                incompatible: target.project.title.clone(),
+                incompatible_chain: target.chain.clone(),
+            });
+        }
+    }
+
+    fn resolve_pending(&mut self, pending: Pending) {
+        let project = match self.project(&pending.project) {
+            Ok(project) => project,
+            Err(err) => {
+                self.fail(&pending.project, err);
+                return;
+            }
+        };
+
+        if let Requirement::VersionId(id) = &pending.requirement {
+            self.pins
+                .entry(project.id.clone())
+                .or_default()
+                .push((id.clone(), pending.chain.clone()));
+        }
+
+        // Reached through another path already
+        if let Some(existing) = self
+            .resolution
+            .mods
+            .iter_mut()
+            .find(|r| r.project.id == project.id)
+        {
+            existing.requested |= pending.requested;
+
+            if let Some(parent) = pending.parent {
+                if !existing.required_by.contains(&parent) {
+                    existing.required_by.push(parent);
+                }
+            }
+
+            return;
+        }
+
+        let unpinned = !matches!(pending.requirement, Requirement::Hash(_));
+
+        if unpinned && self.requires_hash(&project.id) {
+            self.fail(
+                &project.id,
+                Error::NoHash {
+                    project: project.title.clone(),
+                },
+            );
+            return;
+        }
+
+        let version = match self.select_version(&project, &pending.requirement) {
+            Ok(version) => version,
+            Err(err) => {
+                self.fail(&project.slug, err);
+                return;
+            }
+        };
+
+        // A version picked by the manifest counts as a pin too
+        if let (Requirement::Version(_) | Requirement::Hash(_), Some(id)) =
+            (&pending.requirement, &version.id)
+        {
+            self.pins
+                .entry(project.id.clone())
+                .or_default()
+                .push((id.clone(), pending.chain.clone()));
+        }
+
+        let mut chain = pending.chain.clone();
+        chain.push(project.slug.clone());
+
+        for dependency in &version.dependencies {
+            self.enqueue_dependency(&project, &chain, dependency);
+        }
+
+        self.resolution.mods.push(Resolved {
+            project,
+            version,
+            requested: pending.requested,
+            required_by: pending.parent.into_iter().collect(),
+            chain: pending.chain,
+        });
+    }
+
+    // chain is the dependents leading to the dependency, ending with parent
+    fn enqueue_dependency(
+        &mut self,
+        parent: &ModrinthProject,
+        chain: &[String],
+        dependency: &Dependency,
+    ) {
+        let kind = dependency.dependency_type.as_deref().unwrap_or("required");
+
+        let project_id = match (&dependency.project_id, &dependency.version_id) {
+            (Some(project_id), _) => project_id.clone(),
+            (None, Some(version_id)) => match self.version(&parent.id, version_id) {
+                Ok(version) => match version.project_id {
+                    Some(project_id) => project_id,
+                    None => return,
+                },
+                Err(err) => {
+                    self.fail(version_id, err);
+                    return;
+                }
+            },
+            _ => {
+                // Dependencies on files that aren't hosted on Modrinth
+                if kind == "required" {
+                    crate::cwarnln(
+                        format!(
+                            "{} requires {}, which isn't on Modrinth",
+                            style(&parent.title).cyan(),
+                            style(dependency.file_name.as_deref().unwrap_or("a file")).cyan()
+                        )
+                        .as_str(),
+                    );
+                }
+                return;
+            }
+        };
+
+        let requirement = match &dependency.version_id {
+            Some(version_id) => Requirement::VersionId(version_id.clone()),
+            None => Requirement::Latest,
+        };
+
+        match kind {
+            "required" => {}
+            "optional" => {
+                let included = self
+                    .resolution
+                    .mods
+                    .iter()
+                    .any(|r| r.project.id == project_id)
+                    || self.queue.iter().any(|p| p.project == project_id);
+
+                // Only ask about optional dependencies that aren't being installed anyway
+                if !included {
+                    let install = match self.decided.get(&project_id) {
+                        Some(&install) => install,
+                        None => {
+                            let project = match self.project(&project_id) {
+                                Ok(project) => project,
+                                Err(err) => {
+                                    self.fail(&project_id, err);
+                                    return;
+                                }
+                            };
+
+                            let install = (self.optional)(parent, &project);
+                            self.decided.insert(project_id.clone(), install);
+                            install
+                        }
+                    };
+
+                    if !install {
+                        return;
+                    }
+                }
+            }
+            // Bundled inside the dependent's jar already
+            "embedded" => return,
+            "incompatible" => {
+                self.incompatibilities.push(Incompatibility {
+                    declared_by: parent.id.clone(),
+                    project_id,
+                    version_id: dependency.version_id.clone(),
+                });
+                return;
+            }
+            other => {
+                crate::cwarnln(
+                    format!(
+                        "Unknown dependency type {} on {}",
+                        style(other).red(),
+                        style(&parent.title).cyan()
+                    )
+                    .as_str(),
+                );
+                return;
+            }
+        }
+
+        self.queue.push_back(Pending {
+            project: project_id,
+            requirement,
+            parent: Some(parent.id.clone()),
+            chain: chain.to_vec(),
+            requested: false,
+        });
+    }
+
+    // Fetch the projects and versions the queued mods need concurrently, so resolving them
+    // only hits the cache. Failed requests are left for resolve_pending to retry and report.
+    fn prefetch(&mut self) {
+        let wanted = self
+            .queue
+            .iter()
+            .filter(|pending| !self.projects.contains_key(&pending.project))
+            .map(|pending| {
+                // A specific version id only needs that version, otherwise the version list
+                let version = match &pending.requirement {
+                    Requirement::VersionId(id) => Some(id.clone()),
+                    Requirement::Latest => self.preferred.get(&pending.project).cloned(),
+                    Requirement::Version(_) | Requirement::Hash(_) => None,
+                };
+
+                (pending.project.clone(), version)
+            })
+            .collect::<Vec<_>>();
+
+        if wanted.is_empty() {
+            return;
+        }
+
+        let sources = self.sources;
+        let config = self.config;
+
+        let fetched = pool::map(&wanted, config.concurrency, |(id, version)| {
+            let project = sources.project(id).ok();
+            let version = version.as_ref().and_then(|v| sources.version(id, v).ok());
+            let versions = match version {
+                Some(_) => None,
+                None => sources
+                    .supported_versions(id, &config.version, &config.loader)
+                    .ok(),
+            };
+
+            (id.clone(), project, versions, version)
+        });
+
+        for (id, project, versions, version) in fetched {
+            if let Some(project) = project {
+                if let Some(versions) = versions {
+                    self.supported_versions
+                        .insert(project.id.clone(), versions.clone());
+                    self.supported_versions.insert(id.clone(), versions);
+                }
+
+                self.projects.insert(project.id.clone(), project.clone());
+                self.projects.insert(slug_key(&project), project.clone());
+                self.projects.insert(id.clone(), project);
+            }
+
+            if let Some(version) = version {
+                if let Some(version_id) = version.id.clone() {
+                    self.versions.insert(version_id, version);
+                }
+            }
+        }
+    }
+
+    fn select_version(
+        &mut self,
+        project: &ModrinthProject,
+        requirement: &Requirement,
+    ) -> Result<ProjectVersion> {
+        match requirement {
+            Requirement::VersionId(id) => self.version(&project.id, id),
+            Requirement::Version(requirement) => {
+                let versions = self.supported_versions(&project.id)?;
+
+                let found = versions
+                    .iter()
+                    .find(|v| v.id.as_deref() == Some(requirement.as_str()))
+                    .or_else(|| {
+                        versions
+                            .iter()
+                            .find(|v| v.version_number.as_deref() == Some(requirement.as_str()))
+                    })
+                    .cloned();
+
+                // A version number is often shared by the builds for each loader, so only the
+                // ones that fit the config count, but an exact version id is taken as is
+                match found {
+                    Some(version) => Ok(version),
+                    None => self
+                        .version(&project.id, requirement)
+                        .ok()
+                        .filter(|v| v.project_id.as_deref() == Some(project.id.as_str()))
+                        .ok_or_else(|| Error::NoSuchVersion {
+                            project: project.title.clone(),
+                            requirement: requirement.clone(),
+                        }),
+                }
+            }
+            Requirement::Hash(sha512) => {
+                // The pinned file is used whatever it was published for
+                let versions = self.sources.project_versions(&project.id)?;
+
+                let found = versions.iter().find_map(|v| {
+                    let i = v.files.iter().position(|f| &f.hashes.sha512 == sha512)?;
+                    Some((v.clone(), i))
+                });
+
+                // Sources that don't know their files' hashes get the newest file checked
+                // against it when it's downloaded
+                let found = found.or_else(|| {
+                    versions
+                        .into_iter()
+                        .filter(|v| v.primary_file().is_some_and(|f| f.hashes.sha512.is_empty()))
+                        .max_by(|a, b| a.date_published.cmp(&b.date_published))
+                        .map(|v| {
+                            let i = v.files.iter().position(|f| f.primary).unwrap_or(0);
+                            (v, i)
+                        })
+                });
+
+                match found {
+                    Some((mut version, i)) => {
+                        for (j, file) in version.files.iter_mut().enumerate() {
+                            file.primary = i == j;
+                        }
+
+                        version.files[i].hashes.sha512 = sha512.clone();
+
+                        Ok(version)
+                    }
+                    None => Err(Error::NoSuchVersion {
+                        project: project.title.clone(),
+                        requirement: format!("{}{}", HASH_PREFIX, sha512),
+                    }),
+                }
+            }
+            Requirement::Latest => {
+                if let Some(version_id) = self.forced.get(&project.id).cloned() {
+                    return self.version(&project.id, &version_id);
+                }
+
+                if let Some(version_id) = self.preferred.get(&project.id).cloned() {
+                    if let Ok(version) = self.version(&project.id, &version_id) {
+                        if version.supports(&self.config.version, &self.config.loader) {
+                            return Ok(version);
+                        }
+                    }
+                }
+
+                self.supported_versions(&project.id)?
+                    .into_iter()
+                    .max_by(|a, b| a.date_published.cmp(&b.date_published))
+                    .ok_or_else(|| Error::Unsupported {
+                        project: project.title.clone(),
+                        version: self.config.version.clone(),
+                        loader: self.config.loader.clone(),
+                    })
+            }
+        }
+    }
+
+    // Project by slug or id
+    fn project(&mut self, id: &str) -> Result<ModrinthProject> {
+        if let Some(project) = self.projects.get(id) {
+            return Ok(project.clone());
+        }
+
+        let project = self.sources.project(id)?;
+
+        self.projects.insert(project.id.clone(), project.clone());
+        self.projects.insert(slug_key(&project), project.clone());
+        self.projects.insert(id.to_string(), project.clone());
+
+        Ok(project)
+    }
+
+    fn supported_versions(&mut self, id: &str) -> Result<ProjectVersions> {
+        if let Some(versions) = self.supported_versions.get(id) {
+            return Ok(versions.clone());
+        }
+
+        let versions =
+            self.sources
+                .supported_versions(id, &self.config.version, &self.config.loader)?;
+
+        self.supported_versions
+            .insert(id.to_string(), versions.clone());
+
+        Ok(versions)
+    }
+
+    // Version by id, looked up in the source of the project it belongs to
+    fn version(&mut self, project: &str, id: &str) -> Result<ProjectVersion> {
+        if let Some(version) = self.versions.get(id) {
+            return Ok(version.clone());
+        }
+
+        let version = self.sources.version(project, id)?;
+
+        self.versions.insert(id.to_string(), version.clone());
+
+        Ok(version)
+    }
+
+    fn requires_hash(&self, project_id: &str) -> bool {
+        self.sources
+            .get(project_id)
+            .is_ok_and(|(source, _)| source.requires_hash())
+    }
+
+    fn fail(&mut self, project: &str, error: Error) {
+        if !self.resolution.failed.iter().any(|(f, _)| f == project) {
+            self.resolution.failed.push((project.to_string(), error));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{File, ModSource, ModrinthClient};
+
+    const STUB: &str = "stub";
+
+    // Projects and versions held in memory, with ids like stub:a
+    struct Stub {
+        versions: Vec<ProjectVersion>,
+        requires_hash: bool,
+    }
+
+    impl ModSource for Stub {
+        fn name(&self) -> &str {
+            STUB
+        }
+
+        fn project(&self, id: &str) -> Result<ModrinthProject> {
+            Ok(ModrinthProject {
+                id: format!("{}:{}", STUB, id),
+                slug: id.to_string(),
+                title: id.to_string(),
+                ..Default::default()
+            })
+        }
+
+        fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+            let project_id = format!("{}:{}", STUB, id);
+
+            Ok(self
+                .versions
+                .iter()
+                .filter(|v| v.project_id.as_ref() == Some(&project_id))
+                .cloned()
+                .collect())
+        }
+
+        fn version(&self, _project: &str, id: &str) -> Result<ProjectVersion> {
+            self.versions
+                .iter()
+                .find(|v| v.id.as_deref() == Some(id))
+                .cloned()
+                .ok_or_else(|| Error::NoSuchVersion {
+                    project: STUB.to_string(),
+                    requirement: id.to_string(),
+                })
+        }
+
+        fn requires_hash(&self) -> bool {
+            self.requires_hash
+        }
+    }
+
+    // Version id of project, published on day, for fabric on 1.20.1
+    fn version(project: &str, id: &str, day: u32) -> ProjectVersion {
+        ProjectVersion {
+            id: Some(id.to_string()),
+            version_number: Some(id.to_string()),
+            project_id: Some(format!("{}:{}", STUB, project)),
+            game_versions: vec!["1.20.1".to_string()],
+            loaders: vec!["fabric".to_string()],
+            date_published: Some(format!("2024-01-{:02}T00:00:00Z", day)),
+            files: vec![File {
+                url: format!("https://example.com/{}.jar", id),
+                filename: format!("{}.jar", id),
+                primary: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn depends(
+        mut version: ProjectVersion,
+        kind: &str,
+        project: &str,
+        id: Option<&str>,
+    ) -> ProjectVersion {
+        version.dependencies.push(Dependency {
+            version_id: id.map(|id| id.to_string()),
+            project_id: Some(format!("{}:{}", STUB, project)),
+            file_name: None,
+            dependency_type: Some(kind.to_string()),
+        });
+        version
+    }
+
+    fn resolve(versions: Vec<ProjectVersion>, mods: &[(&str, &str)]) -> Resolution {
+        let sources = Sources::new(ModrinthClient::new().unwrap()).with(Stub {
+            versions,
+            requires_hash: false,
+        });
+
+        let config = Config {
+            version: "1.20.1".to_string(),
+            loader: "fabric".to_string(),
+            ..Default::default()
+        };
+
+        let mods = mods
+            .iter()
+            .map(|(id, requirement)| (format!("{}:{}", STUB, id), requirement.to_string()))
+            .collect();
+
+        Resolver::new(&sources, &config).resolve(&mods)
+    }
+
+    fn picked<'r>(resolution: &'r Resolution, project: &str) -> Option<&'r str> {
+        resolution
+            .mods
+            .iter()
+            .find(|r| r.project.slug == project)
+            .and_then(|r| r.version.id.as_deref())
+    }
+
+    #[test]
+    fn prefixed_ids_resolve_through_their_source() {
+        let a = depends(version("a", "a1", 1), "required", "b", None);
+        let resolution = resolve(vec![a, version("b", "b1", 1)], &[("a", LATEST)]);
+
+        let mut ids = resolution
+            .mods
+            .iter()
+            .map(|r| r.project.id.as_str())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(ids, vec!["stub:a", "stub:b"]);
+
+        let sources = Sources::new(ModrinthClient::new().unwrap());
+        let config = Config::default();
+        let mods = BTreeMap::from([("nope:a".to_string(), LATEST.to_string())]);
+        let resolution = Resolver::new(&sources, &config).resolve(&mods);
+
+        assert!(matches!(
+            &resolution.failed[..],
+            [(_, Error::UnknownSource { name })] if name == "nope"
+        ));
+    }
+
+    #[test]
+    fn hash_requirement_pins_the_file() {
+        let mut old = version("a", "a1", 1);
+        old.files[0].hashes.sha512 = "aa".to_string();
+
+        let mut new = version("a", "a2", 2);
+        new.files[0].hashes.sha512 = "bb".to_string();
+
+        let resolution = resolve(vec![old, new], &[("a", "sha512:AA")]);
+
+        assert_eq!(picked(&resolution, "a"), Some("a1"));
+
+        let resolution = resolve(vec![version("a", "a1", 1)], &[("a", "sha512:cc")]);
+        let version = &resolution.mods[0].version;
+
+        // Files without a known hash get the pinned one to be checked against
+        assert_eq!(version.files[0].hashes.sha512, "cc");
+    }
+
+    #[test]
+    fn latest_supported_version_is_picked() {
+        let mut forge = version("a", "a3", 3);
+        forge.loaders = vec!["forge".to_string()];
+
+        let resolution = resolve(
+            vec![version("a", "a1", 1), version("a", "a2", 2), forge],
+            &[("a", LATEST)],
+        );
+
+        assert_eq!(picked(&resolution, "a"), Some("a2"));
+    }
+
+    #[test]
+    fn version_number_pin_skips_other_loaders() {
+        // The same version number for both loaders, forge listed first
+        let mut forge = version("a", "a-forge", 1);
+        forge.version_number = Some("1.0".to_string());
+        forge.loaders = vec!["forge".to_string()];
+
+        let mut fabric = version("a", "a-fabric", 1);
+        fabric.version_number = Some("1.0".to_string());
+
+        let resolution = resolve(vec![forge, fabric], &[("a", "1.0")]);
+
+        assert_eq!(picked(&resolution, "a"), Some("a-fabric"));
+
+        // A version id is taken as is
+        let resolution = resolve(
+            vec![{
+                let mut forge = version("a", "a-forge", 1);
+                forge.loaders = vec!["forge".to_string()];
+                forge
+            }],
+            &[("a", "a-forge")],
+        );
+
+        assert_eq!(picked(&resolution, "a"), Some("a-forge"));
+    }
+
+    #[test]
+    fn dependencies_are_resolved_by_type() {
+        let a = depends(version("a", "a1", 1), "required", "b", None);
+        let a = depends(a, "optional", "c", None);
+        let a = depends(a, "embedded", "d", None);
+
+        let resolution = resolve(
+            vec![
+                a,
+                version("b", "b1", 1),
+                version("c", "c1", 1),
+                version("d", "d1", 1),
+            ],
+            &[("a", LATEST)],
+        );
+
+        assert_eq!(picked(&resolution, "b"), Some("b1"));
+        assert_eq!(picked(&resolution, "c"), None);
+        assert_eq!(picked(&resolution, "d"), None);
+
+        let b = resolution
+            .mods
+            .iter()
+            .find(|r| r.project.slug == "b")
+            .unwrap();
+
+        assert!(!b.requested);
+        assert_eq!(b.required_by, vec!["stub:a"]);
+        assert_eq!(b.chain, vec!["a"]);
+    }
+
+    #[test]
+    fn dependency_pin_wins_over_unpinned_request() {
+        // b is requested unpinned and resolved before c pins an older version of it
+        let c = depends(version("c", "c1", 1), "required", "b", Some("b1"));
+
+        let resolution = resolve(
+            vec![version("b", "b1", 1), version("b", "b2", 2), c],
+            &[("b", LATEST), ("c", LATEST)],
+        );
+
+        assert!(
+            resolution.conflicts.is_empty(),
+            "{:?}",
+            resolution.conflicts
+        );
+        assert_eq!(picked(&resolution, "b"), Some("b1"));
+    }
+
+    #[test]
+    fn dependency_pin_wins_over_unpinned_dependency() {
+        // a needs b unpinned, c needs b1
+        let a = depends(version("a", "a1", 1), "required", "b", None);
+        let c = depends(version("c", "c1", 1), "required", "b", Some("b1"));
+
+        let resolution = resolve(
+            vec![a, c, version("b", "b1", 1), version("b", "b2", 2)],
+            &[("a", LATEST), ("c", LATEST)],
+        );
+
+        assert!(
+            resolution.conflicts.is_empty(),
+            "{:?}",
+            resolution.conflicts
+        );
+        assert_eq!(picked(&resolution, "b"), Some("b1"));
+    }
+
+    #[test]
+    fn sources_requiring_hashes_need_a_hash_requirement() {
+        let sources = Sources::new(ModrinthClient::new().unwrap()).with(Stub {
+            versions: vec![version("a", "a1", 1)],
+            requires_hash: true,
+        });
+
+        let config = Config {
+            version: "1.20.1".to_string(),
+            ..Default::default()
+        };
+
+        let mods = BTreeMap::from([("stub:a".to_string(), LATEST.to_string())]);
+        let resolution = Resolver::new(&sources, &config).resolve(&mods);
+
+        assert!(resolution.mods.is_empty());
+        assert!(matches!(
+            &resolution.failed[..],
+            [(_, Error::NoHash { .. })]
+        ));
+
+        let mods = BTreeMap::from([("stub:a".to_string(), "sha512:aa".to_string())]);
+        let resolution = Resolver::new(&sources, &config).resolve(&mods);
+
+        assert_eq!(picked(&resolution, "a"), Some("a1"));
+    }
+
+    #[test]
+    fn different_pins_conflict() {
+        let a = depends(version("a", "a1", 1), "required", "b", Some("b1"));
+        let c = depends(version("c", "c1", 1), "required", "b", Some("b2"));
+
+        let resolution = resolve(
+            vec![a, c, version("b", "b1", 1), version("b", "b2", 2)],
+            &[("a", LATEST), ("c", LATEST)],
+        );
+
+        match &resolution.conflicts[..] {
+            [Conflict::Version { project, pins, .. }] => {
+                assert_eq!(project, "b");
+
+                let mut versions = pins.iter().map(|p| p.version.as_str()).collect::<Vec<_>>();
+                versions.sort();
+
+                assert_eq!(versions, vec!["b1", "b2"]);
+            }
+            conflicts => panic!("expected a version conflict, got {:?}", conflicts),
+        }
+    }
+
+    #[test]
+    fn manifest_pin_conflicts_with_dependency_pin() {
+        let c = depends(version("c", "c1", 1), "required", "b", Some("b1"));
+
+        let resolution = resolve(
+            vec![version("b", "b1", 1), version("b", "b2", 2), c],
+            &[("b", "b2"), ("c", LATEST)],
+        );
+
+        assert!(matches!(
+            &resolution.conflicts[..],
+            [Conflict::Version { project, .. }] if project == "b"
+        ));
+    }
+
+    #[test]
+    fn incompatible_mods_conflict() {
+        let a = depends(version("a", "a1", 1), "incompatible", "b", None);
+
+        let resolution = resolve(
+            vec![a, version("b", "b1", 1)],
+            &[("a", LATEST), ("b", LATEST)],
+        );
+
+        assert_eq!(
+            resolution.conflicts,
+            vec![Conflict::Incompatible {
+                project: "a".to_string(),
+                chain: Vec::new(),
+                incompatible: "b".to_string(),
+                incompatible_chain: Vec::new(),
+            }]
+        );
+
+        // Only with a specific version of b
+        let a = depends(version("a", "a1", 1), "incompatible", "b", Some("b1"));
+
+        let resolution = resolve(
+            vec![a, version("b", "b1", 1), version("b", "b2", 2)],
+            &[("a", LATEST), ("b", LATEST)],
+        );
+
+        assert!(resolution.conflicts.is_empty());
+
+        // Nothing to conflict with if b isn't installed
+        let a = depends(version("a", "a1", 1), "incompatible", "b", None);
+        let resolution = resolve(vec![a], &[("a", LATEST)]);
+
+        assert!(resolution.conflicts.is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Error, ModrinthClient, ModrinthProject, ProjectVersion, ProjectVersions, Result};
+
+// Name of the default source, ids without a prefix belong to it
+pub const MODRINTH: &str = "modrinth";
+
+// Somewhere mods can be installed from. Projects, versions and files are normalized into
+// Modrinth's shape, with project ids prefixed by the source name (curseforge:238222) unless
+// they come from Modrinth.
+pub trait ModSource: Sync {
+    fn name(&self) -> &str;
+
+    // Project by slug or id, without the source prefix
+    fn project(&self, id: &str) -> Result<ModrinthProject>;
+
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions>;
+
+    // The versions that support a game version and loader. Sources that can filter on their
+    // end instead of listing every version override this.
+    fn supported_versions(
+        &self,
+        id: &str,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<ProjectVersions> {
+        Ok(self
+            .project_versions(id)?
+            .into_iter()
+            .filter(|v| v.supports(game_version, loader))
+            .collect())
+    }
+
+    // Version ids are only unique within a project for some sources
+    fn version(&self, project: &str, id: &str) -> Result<ProjectVersion>;
+
+    // Whether the files can't be trusted unless the manifest pins them with a sha512
+    fn requires_hash(&self) -> bool {
+        false
+    }
+}
+
+impl ModSource for ModrinthClient {
+    fn name(&self) -> &str {
+        MODRINTH
+    }
+
+    fn project(&self, id: &str) -> Result<ModrinthProject> {
+        ModrinthClient::project(self, id)
+    }
+
+    fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        ModrinthClient::project_versions(self, id)
+    }
+
+    fn version(&self, _project: &str, id: &str) -> Result<ProjectVersion> {
+        ModrinthClient::version(self, id)
+    }
+}
+
+// The Modrinth client plus the other sources, picked by the prefix of a project id, e.g.
+// modrinth:sodium, curseforge:238222 or just sodium
+pub struct Sources {
+    client: ModrinthClient,
+    sources: Vec<Box<dyn ModSource>>,
+}
+
+impl Sources {
+    pub fn new(client: ModrinthClient) -> Self {
+        Sources {
+            client,
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, source: impl ModSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    // The Modrinth client, which also does the downloading
+    pub fn client(&self) -> &ModrinthClient {
+        &self.client
+    }
+
+    // The source a project id belongs to and the id without its prefix
+    pub fn get<'b>(&self, id: &'b str) -> Result<(&dyn ModSource, &'b str)> {
+        match id.split_once(':') {
+            None => Ok((&self.client, id)),
+            Some((MODRINTH, id)) => Ok((&self.client, id)),
+            Some((name, id)) => self
+                .sources
+                .iter()
+                .find(|source| source.name() == name)
+                .map(|source| (source.as_ref(), id))
+                .ok_or_else(|| Error::UnknownSource {
+                    name: name.to_string(),
+                }),
+        }
+    }
+
+    pub fn project(&self, id: &str) -> Result<ModrinthProject> {
+        let (source, id) = self.get(id)?;
+        source.project(id)
+    }
+
+    pub fn project_versions(&self, id: &str) -> Result<ProjectVersions> {
+        let (source, id) = self.get(id)?;
+        source.project_versions(id)
+    }
+
+    pub fn supported_versions(
+        &self,
+        id: &str,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<ProjectVersions> {
+        let (source, id) = self.get(id)?;
+        source.supported_versions(id, game_version, loader)
+    }
+
+    // A version of the project with the given (prefixed) id
+    pub fn version(&self, project: &str, id: &str) -> Result<ProjectVersion> {
+        let (source, project) = self.get(project)?;
+        source.version(project, id)
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{lock::LockedMod, Error, Result};
+
+pub const STATE_FILE: &str = "installed.json";
+
+// Every file mdget has installed, kept in the data directory
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    #[serde(skip)]
+    path: PathBuf,
+    pub installs: Vec<Install>,
+    // Absolute paths of the packwiz metafiles mdget wrote, the only ones it removes again
+    #[serde(default)]
+    pub metafiles: Vec<PathBuf>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Install {
+    // Absolute path of the directory the file was installed into
+    pub dir: PathBuf,
+    pub project_id: String,
+    pub slug: String,
+    pub version_id: String,
+    pub version_number: String,
+    pub filename: String,
+    pub sha512: String,
+    // RFC 3339
+    pub installed_at: String,
+    // false if the mod was only pulled in as a dependency
+    pub requested: bool,
+    // Project ids of the mods in the same directory that depend on this one
+    #[serde(default)]
+    pub required_by: Vec<String>,
+}
+
+impl Install {
+    pub fn new(dir: &Path, locked: &LockedMod) -> Self {
+        Install {
+            dir: absolute(dir),
+            project_id: locked.project_id.clone(),
+            slug: locked.slug.clone(),
+            version_id: locked.version_id.clone(),
+            version_number: locked.version_number.clone(),
+            filename: locked.filename.clone(),
+            sha512: locked.hashes.sha512.clone(),
+            installed_at: chrono::Local::now().to_rfc3339(),
+            requested: locked.requested,
+            required_by: locked.required_by.clone(),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.filename)
+    }
+}
+
+impl State {
+    // Load the state from the data directory, starting empty if there is none yet
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(STATE_FILE);
+
+        if !path.exists() {
+            return Ok(State {
+                path,
+                ..Default::default()
+            });
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+
+        match serde_json::from_str::<State>(&content) {
+            Ok(mut state) => {
+                state.path = path;
+                Ok(state)
+            }
+            Err(source) => Err(Error::Json { path, source }),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|source| Error::Json {
+            path: self.path.clone(),
+            source,
+        })?;
+
+        // Write to a temporary file first so a crash can't leave half a state behind
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+
+    // Record an install, replacing whatever was recorded for the project in that directory.
+    // The reasons it was installed for are merged with the ones already recorded.
+    pub fn record(&mut self, mut install: Install) {
+        if let Some(old) = self
+            .installs
+            .iter()
+            .find(|i| i.dir == install.dir && i.project_id == install.project_id)
+        {
+            install.requested |= old.requested;
+
+            for parent in &old.required_by {
+                if !install.required_by.contains(parent) {
+                    install.required_by.push(parent.clone());
+                }
+            }
+        }
+
+        self.installs
+            .retain(|i| !(i.dir == install.dir && i.project_id == install.project_id));
+        self.installs.push(install);
+    }
+
+    // The install of a project in dir, by slug or project id
+    pub fn find(&self, dir: &Path, project: &str) -> Option<&Install> {
+        self.installs_in(dir)
+            .find(|i| i.slug == project || i.project_id == project)
+    }
+
+    // The installs of the given projects in dir, plus the dependencies that nothing needs
+    // once they are gone: ones that weren't requested and whose dependents are all gone too
+    pub fn orphans(&self, dir: &Path, removed: &[String]) -> Vec<Install> {
+        let mut removed = removed.to_vec();
+        let installs = self.installs_in(dir).collect::<Vec<_>>();
+
+        loop {
+            let orphan = installs.iter().find(|i| {
+                !i.requested
+                    && !removed.contains(&i.project_id)
+                    && i.required_by.iter().all(|parent| {
+                        removed.contains(parent)
+                            || !installs.iter().any(|other| &other.project_id == parent)
+                    })
+            });
+
+            match orphan {
+                Some(orphan) => removed.push(orphan.project_id.clone()),
+                None => break,
+            }
+        }
+
+        installs
+            .into_iter()
+            .filter(|i| removed.contains(&i.project_id))
+            .cloned()
+            .collect()
+    }
+
+    // Forget a file that was removed from a directory
+    pub fn forget(&mut self, dir: &Path, filename: &str) {
+        let dir = absolute(dir);
+        self.installs
+            .retain(|i| !(i.dir == dir && i.filename == filename));
+    }
+
+    // Remember the metafiles written into dir, in place of the ones written there before
+    pub fn record_metafiles(&mut self, dir: &Path, metafiles: &[PathBuf]) {
+        let dir = absolute(dir);
+
+        self.metafiles.retain(|path| !path.starts_with(&dir));
+        self.metafiles
+            .extend(metafiles.iter().map(|path| absolute(path)));
+    }
+
+    pub fn wrote_metafile(&self, path: &Path) -> bool {
+        self.metafiles.contains(&absolute(path))
+    }
+
+    pub fn installs_in(&self, dir: &Path) -> impl Iterator<Item = &Install> {
+        let dir = absolute(dir);
+        self.installs.iter().filter(move |i| i.dir == dir)
+    }
+}
+
+fn absolute(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(project_id: &str, requested: bool, required_by: &[&str]) -> Install {
+        Install {
+            dir: PathBuf::from("/nonexistent/mods"),
+            project_id: project_id.to_string(),
+            slug: project_id.to_string(),
+            filename: format!("{}.jar", project_id),
+            requested,
+            required_by: required_by.iter().map(|id| id.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn orphans(state: &State, removed: &[&str]) -> Vec<String> {
+        let removed = removed.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        let mut orphans = state
+            .orphans(Path::new("/nonexistent/mods"), &removed)
+            .into_iter()
+            .map(|install| install.project_id)
+            .collect::<Vec<_>>();
+        orphans.sort();
+        orphans
+    }
+
+    #[test]
+    fn orphans_follow_dependencies() {
+        // app -> lib -> core, and other -> core
+        let state = State {
+            installs: vec![
+                install("app", true, &[]),
+                install("lib", false, &["app"]),
+                install("core", false, &["lib", "other"]),
+                install("other", true, &[]),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(orphans(&state, &["app"]), vec!["app", "lib"]);
+        assert_eq!(
+            orphans(&state, &["app", "other"]),
+            vec!["app", "core", "lib", "other"]
+        );
+        assert!(orphans(&state, &[]).is_empty());
+    }
+
+    #[test]
+    fn orphans_keep_requested_dependencies() {
+        let state = State {
+            installs: vec![install("app", true, &[]), install("lib", true, &["app"])],
+            ..Default::default()
+        };
+
+        assert_eq!(orphans(&state, &["app"]), vec!["app"]);
+    }
+
+    #[test]
+    fn orphans_include_dependencies_of_uninstalled_mods() {
+        // lib's only dependent isn't installed anymore
+        let state = State {
+            installs: vec![install("lib", false, &["gone"])],
+            ..Default::default()
+        };
+
+        assert_eq!(orphans(&state, &[]), vec!["lib"]);
+    }
+
+    #[test]
+    fn orphans_only_look_in_dir() {
+        let mut elsewhere = install("app", true, &[]);
+        elsewhere.dir = PathBuf::from("/nonexistent/other");
+
+        let state = State {
+            installs: vec![elsewhere, install("lib", false, &["app"])],
+            ..Default::default()
+        };
+
+        assert_eq!(orphans(&state, &[]), vec!["lib"]);
+    }
+}